@@ -3,13 +3,29 @@ mod processor {
     pub mod log_processor_options;
     pub mod output_log {
         pub mod count_output;
+        pub mod csv_output;
+        pub mod histogram_output;
         pub mod json_output;
         pub mod json_pretty_output;
+        pub mod logfmt_output;
         pub mod summary_output;
+        pub mod top_output;
+    }
+    pub mod decode_log {
+        pub mod clf_decode;
+        pub mod compression;
+        pub mod csv_decode;
+        pub mod journal_decode;
+        pub mod json_decode;
+        pub mod log_decoder;
+        pub mod logfmt_decode;
+        pub mod multiline;
+        pub mod syslog_decode;
     }
     pub mod get_log {
         pub mod es_get;
         pub mod file_get;
+        pub mod follow_reader;
         pub mod url_get;
     }
     pub mod save_log {
@@ -18,8 +34,20 @@ mod processor {
     }
     pub mod command;
     pub mod db;
+    pub mod duration;
+    pub mod es_regexp;
+    pub mod grok;
     pub mod log_entry;
+    pub mod log_filter;
+    pub mod log_parser;
+    pub mod log_stream;
     pub mod log_trait;
+    pub mod message_search;
+    pub mod quantile_sketch;
+    pub mod route;
+    pub mod time_range;
+    pub mod timestamp;
+    pub mod where_expr;
 }
 
 use clap::Parser;
@@ -30,7 +58,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
     let cli: Cli = Cli::parse();
 
-    let processor = LogProcessorFactory::from_cli(&cli)?;
+    let mut processor = LogProcessorFactory::from_cli(&cli)?;
     processor.run()?;
     Ok(())
 }
@@ -8,14 +8,110 @@ pub struct Cli {
     #[command(flatten)]
     pub input: InputCommand,
 
+    /// format of file and url input: auto, json, text, clf (nginx/apache common and combined), logfmt, syslog,
+    /// journal (journalctl -o export or -o json), csv, tsv
+    #[arg(long, default_value = "auto")]
+    pub input_format: String,
+
+    /// COLUMN=FIELD, read this csv/tsv column into the log field. other columns are read into
+    /// the field named by their header
+    #[arg(long)]
+    pub column_map: Vec<String>,
+
+    /// join stack traces and other continuation lines of text input into the entry before
+    /// them, in its error field
+    #[arg(long)]
+    pub multiline: bool,
+
+    /// regex matching the first line of an entry, other lines continue the entry. implies
+    /// --multiline
+    #[arg(long)]
+    pub multiline_start: Option<String>,
+
+    /// regex matching the lines that continue an entry. implies --multiline
+    #[arg(long)]
+    pub multiline_continuation: Option<String>,
+
+    /// most lines kept in one entry, further continuation lines are dropped
+    #[arg(long, default_value_t = 500)]
+    pub multiline_max_lines: usize,
+
+    /// seconds follow mode waits for more continuation lines before showing an entry
+    #[arg(long, default_value_t = 1.0)]
+    pub multiline_timeout: f64,
+
+    /// strptime format of the timestamps, e.g. "%d.%m.%Y %H:%M:%S". tried in order after
+    /// rfc 3339, epoch numbers and rfc 2822, before the common built-in formats
+    #[arg(long)]
+    pub timestamp_format: Vec<String>,
+
+    /// timezone of timestamps without an offset: local, an offset like +02:00 or a name like
+    /// Europe/Berlin. defaults to UTC
+    #[arg(long)]
+    pub timezone: Option<String>,
+
     /// reverse before limit logs message
     #[arg(long, short)]
     pub reverse: bool,
 
-    /// filter by level
+    /// filter by level, or comma separated levels: debug, info, warn, error, none
     #[arg(long)]
     pub level: Option<String>,
 
+    /// only keep logs at this level or more severe: debug < info < warn < error
+    #[arg(long)]
+    pub min_level: Option<String>,
+
+    /// only keep logs with these http status codes, comma separated: a code (404), a class
+    /// (5xx) or a range (400-499)
+    #[arg(long)]
+    pub status: Option<String>,
+
+    /// only keep logs that took longer than this, e.g. 500ms, 1.5s or 2m. a plain number is
+    /// milliseconds
+    #[arg(long)]
+    pub slower_than: Option<String>,
+
+    /// NAME=VALUE, only keep logs where the field equals the value. works on extra fields too
+    #[arg(long)]
+    pub field: Vec<String>,
+
+    /// filter expression, e.g. 'status_code >= 500 and url ~ "^/api" and not ip_address in
+    /// 10.0.0.0/8'. operators: == != > >= < <= ~ !~ in, and, or, not, parentheses. runs in
+    /// elastic search for es input
+    #[arg(long)]
+    pub r#where: Vec<String>,
+
+    /// only keep logs from these client addresses or CIDR ranges, ipv4 or ipv6, comma
+    /// separated, e.g. 10.0.0.0/8,2001:db8::/32
+    #[arg(long, value_delimiter = ',')]
+    pub ip: Vec<String>,
+
+    /// drop logs from these client addresses or CIDR ranges, e.g. health checks from
+    /// 10.0.0.5,10.1.0.0/16
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_ip: Vec<String>,
+
+    /// only keep logs whose message matches the regex
+    #[arg(long)]
+    pub grep: Option<String>,
+
+    /// only keep logs whose message contains all these words
+    #[arg(long)]
+    pub search: Vec<String>,
+
+    /// make --grep and --search case-insensitive
+    #[arg(long, short)]
+    pub ignore_case: bool,
+
+    /// only keep logs that don't match --grep and --search
+    #[arg(long)]
+    pub invert_match: bool,
+
+    /// color the --grep and --search matches in the output message, only with --pretty-json
+    #[arg(long)]
+    pub highlight: bool,
+
     /// return logs as json text
     #[arg(short('j'), long, short)]
     pub json: bool,
@@ -32,10 +128,15 @@ pub struct Cli {
     #[arg(long, short, group = "output")]
     pub count: bool,
 
-    /// return logs as json text
+    /// print a json summary: counts per method, top clients, latency percentiles per route
+    /// and method, and the share of each status class
     #[arg(long, short, group = "output")]
     pub summary: bool,
 
+    /// count the values of this field in the summary. works on extra fields too
+    #[arg(long, requires = "summary")]
+    pub summary_field: Vec<String>,
+
     /// save logs to elastic search index
     #[arg(short('e'), long, group = "save")]
     pub save_to_es_index: Option<String>,
@@ -44,24 +145,105 @@ pub struct Cli {
     #[arg(long, short)]
     pub limit: Option<i64>,
 
+    /// today, yesterday, N- for the last N days or A_B for the hours A to B of today
     #[arg(long)]
     pub date_filter: Option<String>,
 
+    /// only keep logs from this time on: 2024-03-01T10:00:00Z, 2024-03-01, 2024-03-01 10:00,
+    /// today, yesterday, or a duration ago like 15m, 2h, 3d. uses --timezone
+    #[arg(long)]
+    pub since: Option<String>,
+
+    /// only keep logs before this time, in the formats of --since. a plain date, today or
+    /// yesterday includes that whole day
+    #[arg(long)]
+    pub until: Option<String>,
+
     /// show pretty in json output
     #[arg(long, short, group = "output")]
     pub pretty_json: bool,
 
+    /// return logs as logfmt lines
+    #[arg(long, group = "output")]
+    pub logfmt: bool,
+
+    /// return logs as csv
+    #[arg(long, group = "output")]
+    pub csv: bool,
+
+    /// return logs as tsv
+    #[arg(long, group = "output")]
+    pub tsv: bool,
+
+    /// comma separated fields to print as csv/tsv columns. works on extra fields too
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// count logs per time bucket, per level and per http status class
+    #[arg(long, group = "output")]
+    pub histogram: bool,
+
+    /// print the most frequent values of this field with their count and percentage, e.g.
+    /// url, ip_address, status_code, level, error or an extra field
+    #[arg(long, group = "output")]
+    pub top: Option<String>,
+
+    /// number of values printed by --top, 10 by default
+    #[arg(short('n'), long)]
+    pub top_count: Option<usize>,
+
+    /// rank the --top values separately for each value of this field
+    #[arg(long)]
+    pub group_by: Option<String>,
+
+    /// bucket size of --histogram, e.g. 1m, 5m, 1h or 1d, at least 1s
+    #[arg(long, default_value = "5m")]
+    pub interval: String,
+
+    /// how --histogram prints the buckets: chart, json or csv
+    #[arg(long, default_value = "chart")]
+    pub histogram_format: String,
+
     /// show pretty in json output
     #[arg(long, short)]
     pub verbose: bool,
+
+    /// add the file each log was read from as source_file
+    #[arg(long)]
+    pub source_file: bool,
+
+    /// keep watching the file or url and show new logs as they are written, like tail -f
+    #[arg(long)]
+    pub follow: bool,
+
+    /// seconds between checks for new logs in follow mode
+    #[arg(long, default_value_t = 1.0)]
+    pub poll_interval: f64,
+
+    /// NAME=REGEX or NAME=GROK parser for the message, named groups fill the log fields of the same name. tried in order
+    #[arg(long)]
+    pub parser: Vec<String>,
+
+    /// json file with {"parsers": [{"name": .., "pattern": ..}], "grok_patterns": {..}}, tried after --parser
+    #[arg(long)]
+    pub parser_config: Option<PathBuf>,
+
+    /// logstash grok patterns file, one NAME PATTERN per line, usable in grok parsers
+    #[arg(long)]
+    pub grok_patterns: Vec<PathBuf>,
+
+    /// route template like /users/:user_id/orders/:id or /static/*, urls matching it get it
+    /// as their route. others get numbers, uuids and hashes replaced by :id. tried in order
+    #[arg(long)]
+    pub route: Vec<String>,
 }
 
 #[derive(Args, Debug)]
 #[group(required = true, multiple = false)]
 pub struct InputCommand {
-    /// INPUT COMMAND : input logs from file
-    #[arg(short('F'), long)]
-    pub input_file: Option<PathBuf>,
+    /// INPUT COMMAND : input logs from files, directories or globs, `-` for stdin. gz, zst and bz2 are decompressed
+    #[arg(short('F'), long, num_args = 1..)]
+    pub input_file: Option<Vec<PathBuf>>,
 
     /// INPUT COMMAND : input logs from url
     #[arg(short('U'), long)]
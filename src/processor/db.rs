@@ -24,10 +24,10 @@ impl EsClient {
 
         let url = Url::parse(&host)
             .map_err(|err| format!("failed to create elastic url host : {}", err))?;
-        let elastic_use_cert_validation: bool = match elastic_use_cert_validation.as_str() {
-            "True" | "TRUE" | "true" | "t" | "1" => true,
-            _ => false,
-        };
+        let elastic_use_cert_validation: bool = matches!(
+            elastic_use_cert_validation.as_str(),
+            "True" | "TRUE" | "true" | "t" | "1"
+        );
 
         let conn_pool = SingleNodeConnectionPool::new(url);
 
@@ -0,0 +1,114 @@
+use chrono::DateTime;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::processor::log_entry::{HTTPMethod, LogEntry, LogLevel};
+
+fn clf_regex() -> &'static Regex {
+    static CLF_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    // common log format, optionally followed by the combined referrer and user agent and by
+    // nginx $request_time
+    CLF_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<ip_address>\S+) (?P<ident>\S+) (?P<remote_user>\S+) \[(?P<time>[^\]]+)\] "(?P<request>(?:[^"\\]|\\.)*)" (?P<status_code>\d{3}|-) (?P<bytes_sent>\d+|-)(?: "(?P<referrer>(?:[^"\\]|\\.)*)" "(?P<user_agent>(?:[^"\\]|\\.)*)")?(?: (?P<request_time>\d+(?:\.\d+)?))?"#,
+        )
+        .unwrap()
+    })
+}
+
+/// Returns true when `line` is in the Common or Combined Log Format.
+pub fn is_clf_line(line: &str) -> bool {
+    clf_regex().is_match(line)
+}
+
+/// Parses a Common or Combined Log Format line, as written by nginx and Apache. Lines that
+/// don't match are kept as a plain message.
+pub fn decode_clf_line(line: &str) -> Result<LogEntry, String> {
+    let mut log = LogEntry::from_message(line.to_string());
+    let Some(captures) = clf_regex().captures(line) else {
+        return Ok(log);
+    };
+
+    log.ip_address = captures["ip_address"].to_string();
+    if let Ok(time) = DateTime::parse_from_str(&captures["time"], "%d/%b/%Y:%H:%M:%S %z") {
+        log.timestamp = time.to_rfc3339();
+        log.time_unix = Some(time.timestamp_millis());
+    } else {
+        log.timestamp = captures["time"].to_string();
+    }
+
+    let mut request = captures["request"].splitn(3, ' ');
+    if let (Some(method), Some(url)) = (request.next(), request.next()) {
+        log.http_method = method.parse().unwrap_or(HTTPMethod::NONE);
+        log.url = url.to_string();
+        if let Some(protocol) = request.next() {
+            log.extra_fields
+                .insert("protocol".to_string(), Value::String(protocol.to_string()));
+        }
+    }
+
+    log.status_code = captures["status_code"].parse().ok();
+    log.level = match log.status_code {
+        Some(500..=599) => LogLevel::ERROR,
+        Some(400..=499) => LogLevel::WARN,
+        Some(_) => LogLevel::INFO,
+        None => LogLevel::NONE,
+    };
+
+    // $request_time is in seconds with millisecond resolution
+    if let Some(request_time) = captures.name("request_time") {
+        log.process_time = request_time.as_str().parse::<f64>().unwrap_or(0f64) * 1000f64;
+    }
+
+    for name in ["remote_user", "bytes_sent", "referrer", "user_agent"] {
+        match captures.name(name).map(|value| value.as_str()) {
+            None | Some("-") => {}
+            Some(value) if name == "bytes_sent" => {
+                let bytes_sent = value.parse::<u64>().map(Value::from);
+                log.extra_fields
+                    .insert(name.to_string(), bytes_sent.unwrap_or(Value::Null));
+            }
+            Some(value) => {
+                log.extra_fields
+                    .insert(name.to_string(), Value::String(value.to_string()));
+            }
+        }
+    }
+
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_combined_with_request_time() {
+        let log = decode_clf_line(
+            r#"203.0.113.9 - alice [10/Oct/2023:13:55:36 +0000] "GET /api/users?page=2 HTTP/1.1" 503 1234 "https://example.com/" "curl/8.0" 0.250"#,
+        )
+        .unwrap();
+        assert_eq!(log.ip_address, "203.0.113.9");
+        assert_eq!(log.http_method, HTTPMethod::GET);
+        assert_eq!(log.url, "/api/users?page=2");
+        assert_eq!(log.status_code, Some(503));
+        assert_eq!(log.level, LogLevel::ERROR);
+        assert_eq!(log.process_time, 250f64);
+        assert_eq!(log.time_unix, Some(1696946136000));
+        assert_eq!(log.field("user_agent").as_deref(), Some("curl/8.0"));
+        assert_eq!(log.field("bytes_sent").as_deref(), Some("1234"));
+    }
+
+    #[test]
+    fn test_decode_common() {
+        let line =
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#;
+        assert!(is_clf_line(line));
+        let log = decode_clf_line(line).unwrap();
+        assert_eq!(log.url, "/apache_pb.gif");
+        assert_eq!(log.field("remote_user"), None);
+        assert_eq!(log.field("referrer"), None);
+    }
+}
@@ -0,0 +1,120 @@
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use log::trace;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+/// Path given to `--input-file` to read logs from stdin instead of a file.
+pub const STDIN_PATH: &str = "-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    None,
+}
+
+impl Compression {
+    fn from_extension(path: &Path) -> Option<Compression> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" | "bzip2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    fn from_magic(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Opens `path`, or stdin when the path is `-`, and transparently decompresses gzip, zstd
+/// and bzip2 content. The format is taken from the extension, falling back to magic bytes.
+pub fn open_reader(path: &Path) -> Result<Box<dyn BufRead>, String> {
+    let mut reader: Box<dyn BufRead> = if path == Path::new(STDIN_PATH) {
+        trace!("open_reader: stdin");
+        Box::new(io::stdin().lock())
+    } else {
+        trace!("open_reader: {}", path.display());
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|err| format!("Failed opening file: {}", err))?;
+        Box::new(BufReader::new(file))
+    };
+
+    let compression = match Compression::from_extension(path) {
+        Some(compression) => compression,
+        None => {
+            let magic = reader
+                .fill_buf()
+                .map_err(|err| format!("Failed reading file: {}", err))?;
+            Compression::from_magic(magic)
+        }
+    };
+    trace!("open_reader: compression {:?}", compression);
+
+    decompress(reader, compression)
+}
+
+fn decompress(
+    reader: Box<dyn BufRead>,
+    compression: Compression,
+) -> Result<Box<dyn BufRead>, String> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(reader)
+                .map_err(|err| format!("Failed opening zstd stream: {}", err))?;
+            Box::new(BufReader::new(decoder))
+        }
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Compression::None => reader,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_decompress_by_magic() {
+        let body = b"{\"timestamp\":\"t1\",\"level\":\"info\",\"message\":\"a\"}\n";
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(body).unwrap();
+        let zstd = zstd::encode_all(&body[..], 0).unwrap();
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(body).unwrap();
+
+        for compressed in [
+            gzip.finish().unwrap(),
+            zstd,
+            bzip2.finish().unwrap(),
+            body.to_vec(),
+        ] {
+            let compression = Compression::from_magic(&compressed);
+            let reader: Box<dyn BufRead> = Box::new(io::Cursor::new(compressed));
+            let mut decoded = String::new();
+            decompress(reader, compression)
+                .unwrap()
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded.as_bytes(), body);
+        }
+    }
+}
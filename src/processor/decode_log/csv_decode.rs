@@ -0,0 +1,84 @@
+use log::debug;
+use serde_json::Value;
+use std::io::BufRead;
+
+use crate::processor::{log_entry::LogEntry, log_trait::LogStream};
+
+/// Decodes delimited text with a header line. Each column is set on the `LogEntry` field
+/// named by `column_map`, or by the column header itself. Columns that name no field go to
+/// `extra_fields`.
+pub fn decode_csv<'a>(
+    reader: Box<dyn BufRead + 'a>,
+    delimiter: u8,
+    column_map: &[(String, String)],
+) -> Result<LogStream<'a>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        // tab separated values are not quoted
+        .quoting(delimiter != b'\t')
+        .flexible(true)
+        .from_reader(reader);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| format!("Failed reading csv header: {}", err))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    for (column, _) in column_map {
+        if !headers.contains(column) {
+            return Err(format!("Unknown column `{}` in --column-map", column));
+        }
+    }
+    let fields: Vec<String> = headers
+        .iter()
+        .map(|header| {
+            column_map
+                .iter()
+                .find(|(column, _)| column == header)
+                .map(|(_, field)| field.clone())
+                .unwrap_or_else(|| header.clone())
+        })
+        .collect();
+    debug!("decode_csv: fields {:?}", fields);
+
+    Ok(Box::new(reader.into_records().map(move |record| {
+        let record = record.map_err(|err| format!("Failed parsing logs: {}", err))?;
+        let mut log = LogEntry::from_message(String::new());
+        for (field, value) in fields.iter().zip(record.iter()) {
+            if !value.is_empty() && !log.set_field(field, value) {
+                log.extra_fields
+                    .insert(field.clone(), Value::String(value.to_string()));
+            }
+        }
+        Ok(log)
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::log_entry::LogLevel;
+
+    #[test]
+    fn test_decode_csv_with_column_map() {
+        let body = "time,severity,message,path,tenant\n2024-03-01T10:00:00Z,warn,\"slow, retrying\",/x,acme\n";
+        let column_map = vec![
+            ("time".to_string(), "timestamp".to_string()),
+            ("severity".to_string(), "level".to_string()),
+            ("path".to_string(), "url".to_string()),
+        ];
+        let logs: Vec<LogEntry> = decode_csv(Box::new(body.as_bytes()), b',', &column_map)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].timestamp, "2024-03-01T10:00:00Z");
+        assert_eq!(logs[0].level, LogLevel::WARN);
+        assert_eq!(logs[0].message, "slow, retrying");
+        assert_eq!(logs[0].url, "/x");
+        assert_eq!(logs[0].field("tenant").as_deref(), Some("acme"));
+
+        let column_map = vec![("nope".to_string(), "url".to_string())];
+        assert!(decode_csv(Box::new(body.as_bytes()), b',', &column_map).is_err());
+    }
+}
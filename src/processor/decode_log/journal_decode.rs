@@ -0,0 +1,204 @@
+use chrono::DateTime;
+use log::trace;
+use serde_json::Value;
+use std::{collections::BTreeMap, io::BufRead};
+
+use crate::processor::{
+    decode_log::{json_decode::peek_byte, syslog_decode::severity_level},
+    log_entry::{LogEntry, LogLevel},
+    log_trait::LogStream,
+};
+
+/// Returns true when `line` starts a `journalctl -o export` entry.
+pub fn is_journal_export_line(line: &str) -> bool {
+    line.starts_with("__CURSOR=") || line.starts_with("__REALTIME_TIMESTAMP=")
+}
+
+/// Returns true when `line` is a `journalctl -o json` entry.
+pub fn is_journal_json_line(line: &str) -> bool {
+    line.starts_with('{')
+        && (line.contains("\"__REALTIME_TIMESTAMP\"") || line.contains("\"__CURSOR\""))
+}
+
+/// Decodes `journalctl -o json` or `journalctl -o export` output, whichever the stream
+/// starts with.
+pub fn decode_journal<'a>(mut reader: Box<dyn BufRead + 'a>) -> Result<LogStream<'a>, String> {
+    if peek_byte(&mut reader)? == Some(b'{') {
+        trace!("decode_journal: json");
+        return Ok(Box::new(reader.lines().filter_map(|line| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(decode_journal_json_line(&line)),
+            Err(err) => Some(Err(format!("Failed reading logs: {}", err))),
+        })));
+    }
+    trace!("decode_journal: export");
+    Ok(Box::new(JournalExportReader { reader }))
+}
+
+/// Parses one `journalctl -o json` entry.
+pub fn decode_journal_json_line(line: &str) -> Result<LogEntry, String> {
+    let fields: BTreeMap<String, Value> =
+        serde_json::from_str(line).map_err(|err| format!("Failed parsing logs: {}", err))?;
+    Ok(journal_entry(fields))
+}
+
+/// Reads the export format: `KEY=value` lines, or for binary values the key, a little
+/// endian u64 length and the raw data, with an empty line after each entry.
+struct JournalExportReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> JournalExportReader<R> {
+    fn next_fields(&mut self) -> Result<Option<BTreeMap<String, Value>>, String> {
+        let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+        let mut line: Vec<u8> = Vec::new();
+        loop {
+            line.clear();
+            let len = self
+                .reader
+                .read_until(b'\n', &mut line)
+                .map_err(|err| format!("Failed reading logs: {}", err))?;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if len == 0 || line.is_empty() {
+                if !fields.is_empty() {
+                    return Ok(Some(fields));
+                }
+                if len == 0 {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            match line.iter().position(|byte| *byte == b'=') {
+                Some(index) => {
+                    let key = String::from_utf8_lossy(&line[..index]).to_string();
+                    let value = String::from_utf8_lossy(&line[index + 1..]).to_string();
+                    fields.insert(key, Value::String(value));
+                }
+                None => {
+                    let key = String::from_utf8_lossy(&line).to_string();
+                    let value = self
+                        .read_binary()
+                        .map_err(|err| format!("Failed reading journal field {}: {}", key, err))?;
+                    fields.insert(key, Value::String(value));
+                }
+            }
+        }
+    }
+
+    fn read_binary(&mut self) -> std::io::Result<String> {
+        let mut len = [0u8; 8];
+        self.reader.read_exact(&mut len)?;
+        let mut data = vec![0u8; u64::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut data)?;
+        // trailing newline
+        self.reader.read_exact(&mut [0u8; 1])?;
+        Ok(String::from_utf8_lossy(&data).to_string())
+    }
+}
+
+impl<R: BufRead> Iterator for JournalExportReader<R> {
+    type Item = Result<LogEntry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_fields()
+            .transpose()
+            .map(|fields| fields.map(journal_entry))
+    }
+}
+
+/// Maps journal fields onto a `LogEntry`. `MESSAGE`, `PRIORITY` and `__REALTIME_TIMESTAMP`
+/// fill the entry, other fields like `_SYSTEMD_UNIT` go to `extra_fields` under their
+/// journal name. Address fields such as `__CURSOR` are dropped.
+fn journal_entry(fields: BTreeMap<String, Value>) -> LogEntry {
+    let mut log = LogEntry::from_message(String::new());
+    for (key, value) in fields {
+        match key.as_str() {
+            "MESSAGE" => log.message = value_text(value),
+            "PRIORITY" => {
+                log.level = value_text(value)
+                    .parse::<u8>()
+                    .map(severity_level)
+                    .unwrap_or(LogLevel::NONE)
+            }
+            "__REALTIME_TIMESTAMP" => {
+                let time = value_text(value)
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(DateTime::from_timestamp_micros);
+                if let Some(time) = time {
+                    log.timestamp = time.to_rfc3339();
+                    log.time_unix = Some(time.timestamp_millis());
+                }
+            }
+            key if key.starts_with("__") => {}
+            _ => {
+                log.extra_fields
+                    .insert(key, Value::String(value_text(value)));
+            }
+        }
+    }
+    log
+}
+
+/// Journal JSON values are strings, byte arrays for binary data, or arrays of those when a
+/// field is repeated.
+fn value_text(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        Value::Null => String::new(),
+        Value::Array(values) if values.iter().all(Value::is_u64) => {
+            let bytes: Vec<u8> = values
+                .iter()
+                .filter_map(|value| value.as_u64().map(|byte| byte as u8))
+                .collect();
+            String::from_utf8_lossy(&bytes).to_string()
+        }
+        Value::Array(values) => values
+            .into_iter()
+            .map(value_text)
+            .collect::<Vec<String>>()
+            .join("\n"),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_journal_export() {
+        let mut export = b"__CURSOR=s=1\n__REALTIME_TIMESTAMP=1709287200123456\nPRIORITY=4\n_SYSTEMD_UNIT=nginx.service\nMESSAGE=first\n\n".to_vec();
+        export.extend_from_slice(b"__REALTIME_TIMESTAMP=1709287201000000\nMESSAGE\n");
+        export.extend_from_slice(&11u64.to_le_bytes());
+        export.extend_from_slice(b"two\nlines\n!\n\n");
+
+        let logs: Vec<LogEntry> = decode_journal(Box::new(&export[..]))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].message, "first");
+        assert_eq!(logs[0].level, LogLevel::WARN);
+        assert_eq!(logs[0].time_unix, Some(1709287200123));
+        assert_eq!(
+            logs[0].field("_SYSTEMD_UNIT").as_deref(),
+            Some("nginx.service")
+        );
+        assert_eq!(logs[0].field("__CURSOR"), None);
+        assert_eq!(logs[1].message, "two\nlines\n!");
+    }
+
+    #[test]
+    fn test_decode_journal_json() {
+        let line = r#"{"__CURSOR":"s=1","__REALTIME_TIMESTAMP":"1709287200000000","PRIORITY":"3","MESSAGE":[104,105],"_PID":"42"}"#;
+        assert!(is_journal_json_line(line));
+        let log = decode_journal_json_line(line).unwrap();
+        assert_eq!(log.message, "hi");
+        assert_eq!(log.level, LogLevel::ERROR);
+        assert_eq!(log.field("_PID").as_deref(), Some("42"));
+    }
+}
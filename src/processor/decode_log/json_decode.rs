@@ -0,0 +1,204 @@
+use log::trace;
+use serde::Deserialize;
+use std::io::BufRead;
+
+use crate::processor::{log_entry::LogEntry, log_trait::LogStream};
+
+/// Decodes JSON logs from any reader, detecting the layout from the first non-whitespace
+/// byte: a top-level array, or a sequence of objects (NDJSON or concatenated objects).
+pub fn decode_json<'a, R: BufRead + 'a>(mut reader: R) -> Result<LogStream<'a>, String> {
+    match peek_byte(&mut reader)? {
+        Some(b'[') => {
+            trace!("decode_json: json array");
+            Ok(Box::new(JsonArrayReader::new(reader)))
+        }
+        Some(b'{') => {
+            trace!("decode_json: json objects");
+            Ok(Box::new(JsonObjectsReader::new(reader)))
+        }
+        Some(byte) => Err(format!(
+            "Failed parsing logs: expected a JSON array or object, found `{}`",
+            byte as char
+        )),
+        None => {
+            trace!("decode_json: empty input");
+            Ok(Box::new(std::iter::empty()))
+        }
+    }
+}
+
+/// Returns the next non-whitespace byte without consuming it.
+pub fn peek_byte<R: BufRead>(reader: &mut R) -> Result<Option<u8>, String> {
+    loop {
+        let buf = reader
+            .fill_buf()
+            .map_err(|err| format!("Failed reading logs: {}", err))?;
+        let Some(&byte) = buf.first() else {
+            return Ok(None);
+        };
+        if !byte.is_ascii_whitespace() {
+            return Ok(Some(byte));
+        }
+        reader.consume(1);
+    }
+}
+
+/// Yields the elements of a top-level JSON array one by one, without reading the whole
+/// array into memory.
+struct JsonArrayReader<R: BufRead> {
+    reader: R,
+    started: bool,
+    done: bool,
+}
+
+impl<R: BufRead> JsonArrayReader<R> {
+    fn new(reader: R) -> Self {
+        JsonArrayReader {
+            reader,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Moves past the array opening or the separator before the next element. Returns
+    /// false once the closing bracket is reached.
+    fn next_element(&mut self) -> Result<bool, String> {
+        let expected = if self.started { b',' } else { b'[' };
+        match peek_byte(&mut self.reader)? {
+            Some(b']') if self.started => {
+                self.reader.consume(1);
+                return Ok(false);
+            }
+            Some(byte) if byte == expected => self.reader.consume(1),
+            Some(byte) => {
+                return Err(format!(
+                    "Failed parsing logs: expected `{}`, found `{}`",
+                    expected as char, byte as char
+                ))
+            }
+            None => return Err("Failed parsing logs: unexpected end of input".to_string()),
+        }
+        if !self.started {
+            self.started = true;
+            if peek_byte(&mut self.reader)? == Some(b']') {
+                self.reader.consume(1);
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for JsonArrayReader<R> {
+    type Item = Result<LogEntry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let log = self.next_element().and_then(|has_next| {
+            if !has_next {
+                return Ok(None);
+            }
+            let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+            LogEntry::deserialize(&mut deserializer)
+                .map(Some)
+                .map_err(|err| format!("Failed parsing logs: {}", err))
+        });
+        match log {
+            Ok(Some(log)) => Some(Ok(log)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Yields whitespace separated JSON objects, which covers both NDJSON and objects
+/// concatenated on the same line.
+struct JsonObjectsReader<R: BufRead> {
+    reader: R,
+    done: bool,
+}
+
+impl<R: BufRead> JsonObjectsReader<R> {
+    fn new(reader: R) -> Self {
+        JsonObjectsReader {
+            reader,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonObjectsReader<R> {
+    type Item = Result<LogEntry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let log = peek_byte(&mut self.reader).and_then(|byte| {
+            if byte.is_none() {
+                return Ok(None);
+            }
+            let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+            LogEntry::deserialize(&mut deserializer)
+                .map(Some)
+                .map_err(|err| format!("Failed parsing logs: {}", err))
+        });
+        match log {
+            Ok(Some(log)) => Some(Ok(log)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(body: &str) -> Result<Vec<LogEntry>, String> {
+        decode_json(body.as_bytes())?.collect()
+    }
+
+    #[test]
+    fn test_decode_json_array() {
+        let logs = read(
+            r#" [ {"timestamp":"t1","level":"info","message":"a"},
+                 {"timestamp":"t2","level":"warn","message":"b"} ] "#,
+        )
+        .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[1].message, "b");
+    }
+
+    #[test]
+    fn test_decode_json_objects() {
+        let ndjson = "{\"timestamp\":\"t1\",\"level\":\"info\",\"message\":\"a\"}\n\
+                      {\"timestamp\":\"t2\",\"level\":\"warn\",\"message\":\"b\"}\n";
+        assert_eq!(read(ndjson).unwrap().len(), 2);
+
+        let concatenated = r#"{"timestamp":"t1","level":"info","message":"a"}{"timestamp":"t2","level":"warn","message":"b"}"#;
+        assert_eq!(read(concatenated).unwrap()[1].message, "b");
+    }
+
+    #[test]
+    fn test_decode_json_empty_and_invalid() {
+        assert_eq!(read("").unwrap().len(), 0);
+        assert_eq!(read("[ ]").unwrap().len(), 0);
+        assert!(read("timestamp").is_err());
+        assert!(read(r#"[{"timestamp":"t1","level":"info","message":"a"}"#).is_err());
+    }
+}
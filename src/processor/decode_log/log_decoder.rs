@@ -0,0 +1,182 @@
+use log::debug;
+use std::{
+    io::{BufRead, ErrorKind},
+    time::Duration,
+};
+
+use crate::processor::{
+    decode_log::{
+        clf_decode::{decode_clf_line, is_clf_line},
+        csv_decode::decode_csv,
+        journal_decode::{
+            decode_journal, decode_journal_json_line, is_journal_export_line, is_journal_json_line,
+        },
+        json_decode::decode_json,
+        logfmt_decode::{decode_logfmt_line, is_logfmt_line},
+        multiline::{MultilineReader, MultilineRules},
+        syslog_decode::{decode_syslog_line, is_syslog_line},
+    },
+    log_entry::LogEntry,
+    log_processor_options::{InputFormat, LogProcessorOptions},
+    log_trait::LogStream,
+};
+
+/// How file and url input is decoded into entries.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    format: InputFormat,
+    column_map: Vec<(String, String)>,
+    multiline: Option<MultilineRules>,
+}
+
+impl DecodeOptions {
+    pub fn create(option: &LogProcessorOptions) -> Result<Self, String> {
+        Ok(DecodeOptions {
+            format: option.input_format,
+            column_map: option.column_map.clone(),
+            multiline: option
+                .multiline
+                .as_ref()
+                .map(MultilineRules::create)
+                .transpose()?,
+        })
+    }
+
+    /// Idle time after which a followed input emits the entry being assembled, only set
+    /// when multiline entries are assembled.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.multiline.as_ref().map(MultilineRules::timeout)
+    }
+}
+
+/// Decodes logs read from `reader` in the given format, sniffing the first line when the
+/// format is `Auto`.
+pub fn decode_logs<'a>(
+    mut reader: Box<dyn BufRead + 'a>,
+    options: &DecodeOptions,
+) -> Result<LogStream<'a>, String> {
+    let format = match options.format {
+        InputFormat::Auto => detect_format(&mut reader)?,
+        format => format,
+    };
+    debug!("decode_logs: {:?}", format);
+
+    let is_line_format = matches!(
+        format,
+        InputFormat::Text | InputFormat::Clf | InputFormat::Logfmt | InputFormat::Syslog
+    );
+    if options.multiline.is_some() && !is_line_format {
+        return Err(format!(
+            "--multiline needs text, clf, logfmt or syslog input, found {:?}",
+            format
+        ));
+    }
+
+    let multiline = &options.multiline;
+    match format {
+        InputFormat::Auto | InputFormat::Json => decode_json(reader),
+        InputFormat::Text => Ok(decode_lines(reader, decode_text_line, multiline)),
+        InputFormat::Clf => Ok(decode_lines(reader, decode_clf_line, multiline)),
+        InputFormat::Logfmt => Ok(decode_lines(reader, decode_logfmt_line, multiline)),
+        InputFormat::Syslog => Ok(decode_lines(reader, decode_syslog_line, multiline)),
+        InputFormat::Journal => decode_journal(reader),
+        InputFormat::Csv => decode_csv(reader, b',', &options.column_map),
+        InputFormat::Tsv => decode_csv(reader, b'\t', &options.column_map),
+    }
+}
+
+/// Decodes a single line, as yielded by line oriented inputs.
+pub fn decode_line(line: &str, options: &DecodeOptions) -> Result<LogEntry, String> {
+    let format = match options.format {
+        InputFormat::Auto => detect_line_format(line),
+        format => format,
+    };
+    match format {
+        InputFormat::Auto | InputFormat::Json => {
+            serde_json::from_str(line).map_err(|err| format!("Failed parsing logs: {}", err))
+        }
+        InputFormat::Text => decode_text_line(line),
+        InputFormat::Clf => decode_clf_line(line),
+        InputFormat::Logfmt => decode_logfmt_line(line),
+        InputFormat::Syslog => decode_syslog_line(line),
+        // the export format spans several lines, line oriented inputs need `-o json`
+        InputFormat::Journal => decode_journal_json_line(line),
+        InputFormat::Csv | InputFormat::Tsv => {
+            Err("Failed parsing logs: csv input needs its header line".to_string())
+        }
+    }
+}
+
+fn detect_format<R: BufRead + ?Sized>(reader: &mut R) -> Result<InputFormat, String> {
+    let buf = loop {
+        match reader.fill_buf() {
+            // a followed input is idle before its first line
+            Err(err) if err.kind() == ErrorKind::TimedOut => continue,
+            buf => break buf.map_err(|err| format!("Failed reading logs: {}", err))?,
+        }
+    };
+    let head = String::from_utf8_lossy(buf);
+    let first_line = head
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    Ok(detect_line_format(first_line))
+}
+
+fn detect_line_format(line: &str) -> InputFormat {
+    let line = line.trim_start();
+    if is_journal_json_line(line) || is_journal_export_line(line) {
+        InputFormat::Journal
+    } else if line.starts_with('{') || line.starts_with('[') {
+        InputFormat::Json
+    } else if is_clf_line(line) {
+        InputFormat::Clf
+    } else if is_syslog_line(line) {
+        InputFormat::Syslog
+    } else if is_logfmt_line(line) {
+        InputFormat::Logfmt
+    } else if line.is_empty() {
+        InputFormat::Json
+    } else {
+        InputFormat::Text
+    }
+}
+
+type LineDecoder = fn(&str) -> Result<LogEntry, String>;
+
+fn decode_text_line(line: &str) -> Result<LogEntry, String> {
+    Ok(LogEntry::from_message(line.to_string()))
+}
+
+/// Decodes every non-empty line with `decode`. With multiline rules only the first line of
+/// each entry is decoded, the continuation lines, usually a stack trace, are appended to
+/// `error`.
+fn decode_lines<'a>(
+    reader: Box<dyn BufRead + 'a>,
+    decode: LineDecoder,
+    multiline: &Option<MultilineRules>,
+) -> LogStream<'a> {
+    let Some(rules) = multiline else {
+        return Box::new(reader.lines().filter_map(move |line| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(decode(&line)),
+            Err(err) => Some(Err(format!("Failed reading logs: {}", err))),
+        }));
+    };
+    Box::new(
+        MultilineReader::new(reader, rules.clone()).map(move |lines| {
+            let lines = lines?;
+            let mut log = decode(&lines[0])?;
+            if lines.len() > 1 {
+                let trace = lines[1..].join("\n");
+                log.error = if log.error.is_empty() {
+                    trace
+                } else {
+                    format!("{}\n{}", log.error, trace)
+                };
+            }
+            Ok(log)
+        }),
+    )
+}
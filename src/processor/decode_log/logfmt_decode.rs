@@ -0,0 +1,131 @@
+use serde_json::Value;
+
+use crate::processor::{
+    duration::parse_duration_ms,
+    log_entry::{LogEntry, LogLevel},
+};
+
+/// Splits a logfmt line into its `key=value` pairs, unquoting quoted values. Keys without a
+/// value get an empty one.
+pub fn parse_logfmt(line: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            key.push(c);
+        }
+        if key.is_empty() && chars.peek().is_none() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(c) => value.push(c),
+                            None => break,
+                        },
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        if !key.is_empty() {
+            pairs.push((key, value));
+        }
+    }
+    pairs
+}
+
+/// Returns true when `line` looks like logfmt, at least two `key=value` pairs.
+pub fn is_logfmt_line(line: &str) -> bool {
+    let pairs = parse_logfmt(line);
+    pairs.len() >= 2
+        && pairs.iter().all(|(key, _)| {
+            key.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '@'))
+        })
+        && line
+            .split_whitespace()
+            .filter(|part| part.contains('='))
+            .count()
+            >= 2
+}
+
+/// Parses a logfmt line, mapping the common key aliases onto the `LogEntry` fields. Other
+/// keys go to `extra_fields`.
+pub fn decode_logfmt_line(line: &str) -> Result<LogEntry, String> {
+    let mut log = LogEntry::from_message(String::new());
+    for (key, value) in parse_logfmt(line) {
+        match key.as_str() {
+            "ts" | "time" | "timestamp" | "t" | "@timestamp" => log.timestamp = value,
+            "level" | "lvl" | "severity" | "loglevel" => {
+                log.level = LogLevel::from_name(&value).unwrap_or(LogLevel::NONE)
+            }
+            "msg" | "message" => log.message = value,
+            "method" | "http_method" | "verb" => {
+                log.set_field("http_method", &value);
+            }
+            "path" | "url" | "uri" | "request_uri" => log.url = value,
+            "status" | "status_code" | "code" => {
+                log.set_field("status_code", &value);
+            }
+            "dur" | "duration" | "latency" | "elapsed" | "took" | "process_time" => {
+                // bare numbers are taken as milliseconds
+                log.process_time = parse_duration_ms(&value)
+                    .or_else(|| value.parse().ok())
+                    .unwrap_or(0f64)
+            }
+            "ip" | "ip_address" | "remote_addr" | "client_ip" => log.ip_address = value,
+            "err" | "error" => log.error = value,
+            "route" => log.route = value,
+            "source_file" => log.source_file = Some(value),
+            _ => {
+                log.extra_fields.insert(key, Value::String(value));
+            }
+        }
+    }
+    if log.message.is_empty() {
+        log.message = line.to_string();
+    }
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::log_entry::HTTPMethod;
+
+    #[test]
+    fn test_decode_logfmt_line() {
+        let line = r#"ts=2024-03-01T10:00:00Z level=warn msg="slow request \"x\"" method=GET path=/x status=200 dur=1.2s trace_id=abc flag"#;
+        assert!(is_logfmt_line(line));
+        let log = decode_logfmt_line(line).unwrap();
+        assert_eq!(log.timestamp, "2024-03-01T10:00:00Z");
+        assert_eq!(log.level, LogLevel::WARN);
+        assert_eq!(log.message, r#"slow request "x""#);
+        assert_eq!(log.http_method, HTTPMethod::GET);
+        assert_eq!(log.url, "/x");
+        assert_eq!(log.status_code, Some(200));
+        assert_eq!(log.process_time, 1200f64);
+        assert_eq!(log.field("trace_id").as_deref(), Some("abc"));
+        assert_eq!(log.field("flag").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_is_logfmt_line() {
+        assert!(!is_logfmt_line("GET /x took 12ms"));
+        assert!(!is_logfmt_line("a=b"));
+    }
+}
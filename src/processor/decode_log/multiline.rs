@@ -0,0 +1,167 @@
+use log::{debug, trace};
+use regex::Regex;
+use std::{
+    io::{BufRead, ErrorKind},
+    time::Duration,
+};
+
+use crate::processor::log_processor_options::MultilineOptions;
+
+/// Continuation lines of Java and Python stack traces: indented frames, `Caused by:`,
+/// `Traceback` and the final `SomeError: message` line.
+pub const DEFAULT_CONTINUATION_PATTERN: &str =
+    r"^(?:\s|Traceback \(|Caused by:|[\w.$]+(?:Error|Exception|Throwable)\b)";
+
+/// Decides which lines continue the previous entry. A line is a continuation when it
+/// matches `continuation`, or when `start` is set and it doesn't match `start`.
+#[derive(Debug, Clone)]
+pub struct MultilineRules {
+    start: Option<Regex>,
+    continuation: Option<Regex>,
+    max_lines: usize,
+    timeout: Duration,
+}
+
+impl MultilineRules {
+    pub fn create(option: &MultilineOptions) -> Result<Self, String> {
+        let compile = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|err| format!("Failed to compile multiline pattern: {}", err))
+        };
+        Ok(MultilineRules {
+            start: compile(&option.start)?,
+            continuation: compile(&option.continuation)?,
+            max_lines: option.max_lines.max(1),
+            timeout: option.timeout,
+        })
+    }
+
+    /// How long follow mode waits for more continuation lines before emitting an entry.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn is_continuation(&self, line: &str) -> bool {
+        self.continuation
+            .as_ref()
+            .is_some_and(|continuation| continuation.is_match(line))
+            || self
+                .start
+                .as_ref()
+                .is_some_and(|start| !start.is_match(line))
+    }
+}
+
+/// Groups the lines of `reader` into entries, each a first line followed by its
+/// continuation lines. A `TimedOut` error from the reader, raised by follow mode while the
+/// input is idle, emits the entry being assembled.
+pub struct MultilineReader<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    rules: MultilineRules,
+    buf: Vec<u8>,
+    pending: Option<Vec<String>>,
+}
+
+impl<'a> MultilineReader<'a> {
+    pub fn new(reader: Box<dyn BufRead + 'a>, rules: MultilineRules) -> Self {
+        MultilineReader {
+            reader,
+            rules,
+            buf: Vec::new(),
+            pending: None,
+        }
+    }
+
+    fn push_line(&mut self, line: String) -> Option<Vec<String>> {
+        match &mut self.pending {
+            Some(lines) if self.rules.is_continuation(&line) => {
+                if lines.len() < self.rules.max_lines {
+                    lines.push(line);
+                } else {
+                    trace!("multiline: dropped line over max lines");
+                }
+                None
+            }
+            _ => self.pending.replace(vec![line]),
+        }
+    }
+}
+
+impl Iterator for MultilineReader<'_> {
+    type Item = Result<Vec<String>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // the buffer keeps a partial line read before a timeout
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return self.pending.take().map(Ok),
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&self.buf)
+                        .trim_end_matches(['\n', '\r'])
+                        .to_string();
+                    self.buf.clear();
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    if let Some(lines) = self.push_line(line) {
+                        return Some(Ok(lines));
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::TimedOut => {
+                    if let Some(lines) = self.pending.take() {
+                        debug!("multiline: timeout, emitting {} lines", lines.len());
+                        return Some(Ok(lines));
+                    }
+                }
+                Err(err) => return Some(Err(format!("Failed reading logs: {}", err))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(start: Option<&str>, continuation: Option<&str>, max_lines: usize) -> MultilineRules {
+        MultilineRules::create(&MultilineOptions {
+            start: start.map(str::to_string),
+            continuation: continuation.map(str::to_string),
+            max_lines,
+            timeout: Duration::from_secs(1),
+        })
+        .unwrap()
+    }
+
+    fn group(body: &'static str, rules: MultilineRules) -> Vec<Vec<String>> {
+        MultilineReader::new(Box::new(body.as_bytes()), rules)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_continuation_pattern() {
+        let body = "ts=1 msg=failed\njava.lang.IllegalStateException: boom\n\tat a.B.c(B.java:1)\nCaused by: java.io.IOException\n\t... 3 more\nts=2 msg=ok\nTraceback (most recent call last):\n  File \"x.py\", line 1\nValueError: bad\n";
+        let entries = group(body, rules(None, Some(DEFAULT_CONTINUATION_PATTERN), 500));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].len(), 5);
+        assert_eq!(entries[1].len(), 4);
+        assert_eq!(entries[1][3], "ValueError: bad");
+    }
+
+    #[test]
+    fn test_start_pattern_and_max_lines() {
+        let body = "2024-01-01 first\nmore\nand more\n2024-01-02 second\n";
+        let entries = group(body, rules(Some(r"^\d{4}-"), None, 2));
+        assert_eq!(
+            entries,
+            vec![
+                vec!["2024-01-01 first".to_string(), "more".to_string()],
+                vec!["2024-01-02 second".to_string()],
+            ]
+        );
+    }
+}
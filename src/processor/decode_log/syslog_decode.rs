@@ -0,0 +1,167 @@
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::processor::log_entry::{LogEntry, LogLevel};
+
+fn rfc5424_regex() -> &'static Regex {
+    static RFC5424_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    RFC5424_REGEX.get_or_init(|| {
+        Regex::new(
+            r"^<(?P<pri>\d{1,3})>1 (?P<timestamp>\S+) (?P<hostname>\S+) (?P<app_name>\S+) (?P<procid>\S+) (?P<msgid>\S+) (?P<structured_data>-|(?:\[(?:[^\]\\]|\\.)*\])+)(?: (?P<message>.*))?$",
+        )
+        .unwrap()
+    })
+}
+
+fn rfc3164_regex() -> &'static Regex {
+    static RFC3164_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    // the priority is missing in files written by syslog daemons, which may also use an
+    // RFC 3339 timestamp in place of the BSD one
+    RFC3164_REGEX.get_or_init(|| {
+        Regex::new(
+            r"^(?:<(?P<pri>\d{1,3})>)?(?P<timestamp>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (?P<hostname>\S+) (?P<app_name>[^\s\[:]+)(?:\[(?P<procid>[^\]]+)\])?: ?(?P<message>.*)$",
+        )
+        .unwrap()
+    })
+}
+
+/// Returns true when `line` is an RFC 5424 or RFC 3164 syslog line.
+pub fn is_syslog_line(line: &str) -> bool {
+    rfc5424_regex().is_match(line) || rfc3164_regex().is_match(line)
+}
+
+/// Parses an RFC 5424 or RFC 3164 syslog line. The severity sets the level, hostname,
+/// app name, procid and msgid go to `extra_fields`. Lines that don't match are kept as a
+/// plain message.
+pub fn decode_syslog_line(line: &str) -> Result<LogEntry, String> {
+    let mut log = LogEntry::from_message(line.to_string());
+    if let Some(captures) = rfc5424_regex().captures(line) {
+        set_header(&mut log, &captures);
+        set_timestamp(
+            &mut log,
+            DateTime::parse_from_rfc3339(&captures["timestamp"]).ok(),
+        );
+        for name in ["msgid", "structured_data"] {
+            set_extra(&mut log, name, &captures);
+        }
+    } else if let Some(captures) = rfc3164_regex().captures(line) {
+        set_header(&mut log, &captures);
+        let timestamp = &captures["timestamp"];
+        let time = DateTime::parse_from_rfc3339(timestamp)
+            .ok()
+            .or_else(|| parse_bsd_timestamp(timestamp));
+        set_timestamp(&mut log, time);
+    }
+    Ok(log)
+}
+
+fn set_header(log: &mut LogEntry, captures: &Captures) {
+    log.message = captures
+        .name("message")
+        .map(|message| message.as_str().trim_start_matches('\u{feff}'))
+        .unwrap_or("")
+        .to_string();
+    if let Some(pri) = captures
+        .name("pri")
+        .and_then(|pri| pri.as_str().parse::<u8>().ok())
+    {
+        log.level = severity_level(pri % 8);
+        log.extra_fields
+            .insert("facility".to_string(), Value::from(pri / 8));
+        log.extra_fields
+            .insert("severity".to_string(), Value::from(pri % 8));
+    }
+    for name in ["hostname", "app_name", "procid"] {
+        set_extra(log, name, captures);
+    }
+}
+
+fn set_extra(log: &mut LogEntry, name: &str, captures: &Captures) {
+    match captures.name(name).map(|value| value.as_str()) {
+        None | Some("-") => {}
+        Some(value) => {
+            log.extra_fields
+                .insert(name.to_string(), Value::String(value.to_string()));
+        }
+    }
+}
+
+fn set_timestamp(log: &mut LogEntry, time: Option<DateTime<chrono::FixedOffset>>) {
+    if let Some(time) = time {
+        log.timestamp = time.to_rfc3339();
+        log.time_unix = Some(time.timestamp_millis());
+    }
+}
+
+/// Parses `Mmm dd hh:mm:ss` in local time. The year is not written, so it is the current
+/// one unless that puts the entry in the future, as happens around new year.
+fn parse_bsd_timestamp(timestamp: &str) -> Option<DateTime<chrono::FixedOffset>> {
+    let now = Local::now();
+    let parse = |year: i32| {
+        let time =
+            NaiveDateTime::parse_from_str(&format!("{} {}", year, timestamp), "%Y %b %e %H:%M:%S")
+                .ok()?;
+        Local.from_local_datetime(&time).earliest()
+    };
+    let time = parse(now.year())?;
+    let time = if time > now + chrono::Duration::days(1) {
+        parse(now.year() - 1)?
+    } else {
+        time
+    };
+    Some(time.fixed_offset())
+}
+
+/// Maps syslog severities, 0 emergency to 7 debug, onto `LogLevel`.
+pub fn severity_level(severity: u8) -> LogLevel {
+    match severity {
+        0..=3 => LogLevel::ERROR,
+        4 => LogLevel::WARN,
+        5 | 6 => LogLevel::INFO,
+        _ => LogLevel::DEBUG,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_rfc5424() {
+        let line = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3"] An application event"#;
+        assert!(is_syslog_line(line));
+        let log = decode_syslog_line(line).unwrap();
+        assert_eq!(log.level, LogLevel::INFO);
+        assert_eq!(log.message, "An application event");
+        assert_eq!(log.time_unix, Some(1065910455003));
+        assert_eq!(
+            log.field("hostname").as_deref(),
+            Some("mymachine.example.com")
+        );
+        assert_eq!(log.field("app_name").as_deref(), Some("evntslog"));
+        assert_eq!(log.field("procid"), None);
+        assert_eq!(log.field("msgid").as_deref(), Some("ID47"));
+        assert_eq!(log.field("facility").as_deref(), Some("20"));
+    }
+
+    #[test]
+    fn test_decode_rfc3164() {
+        let line =
+            "<34>Oct 11 22:14:15 mymachine su[1234]: 'su root' failed for lonvick on /dev/pts/8";
+        let log = decode_syslog_line(line).unwrap();
+        assert_eq!(log.level, LogLevel::ERROR);
+        assert_eq!(log.message, "'su root' failed for lonvick on /dev/pts/8");
+        assert!(log.time_unix.is_some());
+        assert_eq!(log.field("app_name").as_deref(), Some("su"));
+        assert_eq!(log.field("procid").as_deref(), Some("1234"));
+
+        let log = decode_syslog_line("Mar  1 08:00:01 web-1 CRON[42]: (root) CMD (true)").unwrap();
+        assert_eq!(log.level, LogLevel::NONE);
+        assert_eq!(log.field("hostname").as_deref(), Some("web-1"));
+        assert_eq!(log.message, "(root) CMD (true)");
+    }
+}
@@ -0,0 +1,50 @@
+/// Parses durations like `250ms`, `1.5s`, `2h` or Go's `1m2.5s` into milliseconds. Returns
+/// `None` for plain numbers, whose unit depends on the caller.
+pub fn parse_duration_ms(duration: &str) -> Option<f64> {
+    let duration = duration.trim();
+    let mut total = 0f64;
+    let mut rest = duration;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|len| *len > 0)?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit_ms = match &rest[..unit_len] {
+            "ns" => 0.000_001,
+            "us" | "µs" | "μs" => 0.001,
+            "ms" => 1f64,
+            "s" | "sec" => 1_000f64,
+            "m" | "min" => 60_000f64,
+            "h" => 3_600_000f64,
+            "d" => 86_400_000f64,
+            _ => return None,
+        };
+        total += number * unit_ms;
+        rest = &rest[unit_len..];
+    }
+    if duration.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(parse_duration_ms("250ms"), Some(250f64));
+        assert_eq!(parse_duration_ms("1.5s"), Some(1500f64));
+        assert_eq!(parse_duration_ms("1m2.5s"), Some(62500f64));
+        assert_eq!(parse_duration_ms("750µs"), Some(0.75));
+        assert_eq!(parse_duration_ms("3d"), Some(259_200_000f64));
+        assert_eq!(parse_duration_ms("12"), None);
+        assert_eq!(parse_duration_ms("fast"), None);
+        assert_eq!(parse_duration_ms(""), None);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Characters with a meaning in Elasticsearch (Lucene) regexps, escaped when they are meant
+/// literally. `@ & ~ < > #` are operators of the optional syntax that Elasticsearch turns on.
+const RESERVED: &str = ".?+*|{}[]()\"\\@&~<>#";
+
+/// Translates a regex as the `regex` crate reads it into an Elasticsearch `regexp` query
+/// value and whether it is case insensitive. Elasticsearch regexps are always anchored and
+/// have no `\d`-like classes, inline flags or lookaround, so `^` and `$` are only allowed at
+/// the ends and only a leading `(?i)` is kept. Anything else that can't be searched the same
+/// way is an error rather than a query that matches other logs.
+pub fn es_regexp(pattern: &str) -> Result<(String, bool), String> {
+    let (body, case_insensitive) = match pattern.strip_prefix("(?i)") {
+        Some(body) => (body, true),
+        None => (pattern, false),
+    };
+    let fail = |construct: &str| {
+        format!(
+            "Failed to search regex `{}` in elastic search: {} can't be translated",
+            pattern, construct
+        )
+    };
+
+    let mut translator = Translator {
+        chars: body.chars().peekable(),
+        out: String::with_capacity(body.len()),
+        depth: 0,
+        alternation: false,
+        start_anchor: false,
+        end_anchor: false,
+        first: true,
+    };
+    translator
+        .translate()
+        .map_err(|construct| fail(&construct))?;
+
+    let Translator {
+        out,
+        alternation,
+        start_anchor,
+        end_anchor,
+        ..
+    } = translator;
+    if alternation && (start_anchor || end_anchor) {
+        return Err(fail("`^` or `$` with a top level `|`"));
+    }
+    let out = if alternation {
+        format!("({})", out)
+    } else {
+        out
+    };
+    Ok((
+        format!(
+            "{}{}{}",
+            if start_anchor { "" } else { ".*" },
+            out,
+            if end_anchor { "" } else { ".*" }
+        ),
+        case_insensitive,
+    ))
+}
+
+struct Translator<'a> {
+    chars: Peekable<Chars<'a>>,
+    out: String,
+    depth: usize,
+    alternation: bool,
+    start_anchor: bool,
+    end_anchor: bool,
+    first: bool,
+}
+
+impl Translator<'_> {
+    /// Errors with the construct that has no Elasticsearch equivalent.
+    fn translate(&mut self) -> Result<(), String> {
+        while let Some(c) = self.chars.next() {
+            let first = std::mem::replace(&mut self.first, false);
+            match c {
+                '^' if first => self.start_anchor = true,
+                '$' if self.chars.peek().is_none() && self.depth == 0 => self.end_anchor = true,
+                '^' | '$' => return Err(format!("`{}` inside the regex", c)),
+                '\\' => self.escape()?,
+                '[' => self.class()?,
+                '(' => self.group()?,
+                ')' => {
+                    self.depth -= 1;
+                    self.out.push(')');
+                }
+                '|' => {
+                    if self.depth == 0 {
+                        self.alternation = true;
+                    }
+                    self.out.push('|');
+                }
+                // `.` doesn't match a new line without the `s` flag
+                '.' => self.out.push_str("[^\n]"),
+                '*' | '+' | '?' => {
+                    self.out.push(c);
+                    self.skip_lazy();
+                }
+                '{' => {
+                    self.out.push('{');
+                    for c in self.chars.by_ref() {
+                        self.out.push(c);
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                    self.skip_lazy();
+                }
+                c => push_literal(&mut self.out, c),
+            }
+        }
+        Ok(())
+    }
+
+    /// A lazy quantifier matches the same logs as a greedy one.
+    fn skip_lazy(&mut self) {
+        self.chars.next_if_eq(&'?');
+    }
+
+    fn group(&mut self) -> Result<(), String> {
+        if self.chars.next_if_eq(&'?').is_some() {
+            match self.chars.next() {
+                Some(':') => {}
+                Some('P') if self.chars.peek() == Some(&'<') => self.skip_group_name(),
+                Some('<') => self.skip_group_name(),
+                _ => return Err("inline flags and lookaround".to_string()),
+            }
+        }
+        self.depth += 1;
+        self.out.push('(');
+        Ok(())
+    }
+
+    fn skip_group_name(&mut self) {
+        for c in self.chars.by_ref() {
+            if c == '>' {
+                break;
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<(), String> {
+        let Some(c) = self.chars.next() else {
+            return Err("a trailing `\\`".to_string());
+        };
+        match c {
+            'd' => self.out.push_str("[0-9]"),
+            'D' => self.out.push_str("[^0-9]"),
+            'w' => self.out.push_str("[a-zA-Z0-9_]"),
+            'W' => self.out.push_str("[^a-zA-Z0-9_]"),
+            's' => self.out.push_str("[ \t\n\r\x0b\x0c]"),
+            'S' => self.out.push_str("[^ \t\n\r\x0b\x0c]"),
+            c => push_literal(&mut self.out, escaped_char(c)?),
+        }
+        Ok(())
+    }
+
+    /// Copies a `[...]` class. Only plain characters, ranges and `\d`, `\w` and `\s` are
+    /// kept, nested classes and class operations are errors.
+    fn class(&mut self) -> Result<(), String> {
+        self.out.push('[');
+        if self.chars.next_if_eq(&'^').is_some() {
+            self.out.push('^');
+        }
+        // a `]` right at the start is literal
+        if self.chars.next_if_eq(&']').is_some() {
+            self.out.push_str("\\]");
+        }
+        while let Some(c) = self.chars.next() {
+            match c {
+                ']' => {
+                    self.out.push(']');
+                    return Ok(());
+                }
+                '[' => return Err("a nested character class".to_string()),
+                '&' | '-' | '~' if self.chars.peek() == Some(&c) => {
+                    return Err(format!("`{}{}` in a character class", c, c))
+                }
+                '-' => self.out.push('-'),
+                '\\' => match self.chars.next() {
+                    Some('d') => self.out.push_str("0-9"),
+                    Some('w') => self.out.push_str("a-zA-Z0-9_"),
+                    Some('s') => self.out.push_str(" \t\n\r\x0b\x0c"),
+                    Some(c @ ('D' | 'W' | 'S')) => {
+                        return Err(format!("`\\{}` in a character class", c))
+                    }
+                    Some(c) => push_literal(&mut self.out, escaped_char(c)?),
+                    None => return Err("a trailing `\\`".to_string()),
+                },
+                c => push_literal(&mut self.out, c),
+            }
+        }
+        Err("an unclosed character class".to_string())
+    }
+}
+
+/// The character written as `\c`, for the escapes that stand for a single character.
+fn escaped_char(c: char) -> Result<char, String> {
+    match c {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        'f' => Ok('\x0c'),
+        'v' => Ok('\x0b'),
+        'a' => Ok('\x07'),
+        c if c.is_ascii_alphanumeric() => Err(format!("`\\{}`", c)),
+        c => Ok(c),
+    }
+}
+
+fn push_literal(out: &mut String, c: char) {
+    if RESERVED.contains(c) {
+        out.push('\\');
+    }
+    out.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_es_regexp() {
+        assert_eq!(es_regexp("^/api"), Ok(("/api.*".to_string(), false)));
+        assert_eq!(
+            es_regexp(r"(?i)order \d+$"),
+            Ok((".*order [0-9]+".to_string(), true))
+        );
+        assert_eq!(
+            es_regexp(r"^\D\S\W$"),
+            Ok(("[^0-9][^ \t\n\r\x0b\x0c][^a-zA-Z0-9_]".to_string(), false))
+        );
+        // an escaped backslash followed by a d
+        assert_eq!(es_regexp(r"^\\d$"), Ok((r"\\d".to_string(), false)));
+        // reserved characters of elasticsearch are literal
+        assert_eq!(
+            es_regexp("^a@b&c~d<e>f#g\"$"),
+            Ok((r#"a\@b\&c\~d\<e\>f\#g\""#.to_string(), false))
+        );
+        assert_eq!(
+            es_regexp(r"^[\d_.@-]+?(?:x|y)$"),
+            Ok((r"[0-9_\.\@-]+(x|y)".to_string(), false))
+        );
+        assert_eq!(
+            es_regexp("a.b|c"),
+            Ok((".*(a[^\n]b|c).*".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn test_es_regexp_errors() {
+        for pattern in [
+            r"\bword\b",
+            r"a(?i)b",
+            r"(?s)a.b",
+            r"(?=a)",
+            r"a^b",
+            r"a$b",
+            r"^a|b",
+            r"\p{L}",
+            r"\x41",
+            r"[[:alpha:]]",
+            r"[a-z&&[^x]]",
+            r"[\D]",
+        ] {
+            assert!(es_regexp(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
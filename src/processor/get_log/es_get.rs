@@ -1,4 +1,4 @@
-use elasticsearch::SearchParts;
+use elasticsearch::{ClearScrollParts, ScrollParts, SearchParts};
 use log::{debug, trace};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -7,16 +7,38 @@ use tokio::runtime::Runtime;
 use crate::processor::{
     db::EsClient,
     log_entry::{LogEntry, LogLevel},
-    log_processor_options::LogProcessorOptions, log_trait::GetLogTrait,
+    log_parser::ParserRegistry,
+    log_processor_options::LogProcessorOptions,
+    log_stream::process_logs,
+    log_trait::{GetLogTrait, LogStream},
+    message_search::MessageSearch,
+    route::RouteNormalizer,
+    time_range::TimeRange,
+    timestamp::TimestampParser,
+    where_expr::WhereExpr,
 };
 
+/// Hits fetched per request while scrolling.
+const PAGE_SIZE: i64 = 1000;
+
+/// How long Elasticsearch keeps the scroll between two pages.
+const SCROLL_KEEP_ALIVE: &str = "1m";
+
 pub struct ESGetStrategy<'a> {
     index: String,
     reverse: bool,
     limit: i64,
-    date_filter_string: Option<String>,
-    date_filter: Option<(i64, i64)>,
-    level_filter: Option<LogLevel>,
+    time_range: Option<TimeRange>,
+    level_filter: Option<Vec<LogLevel>>,
+    status_filter: Vec<(u16, u16)>,
+    slower_than: Option<f64>,
+    field_filters: Vec<(String, String)>,
+    where_query: Option<Value>,
+    message_query: Option<Value>,
+    message_search: Option<MessageSearch>,
+    parsers: ParserRegistry,
+    routes: RouteNormalizer,
+    timestamps: TimestampParser,
     es_client: &'a EsClient,
 }
 impl<'a> ESGetStrategy<'a> {
@@ -25,13 +47,32 @@ impl<'a> ESGetStrategy<'a> {
         option: LogProcessorOptions,
         es_client: &'a EsClient,
     ) -> Result<Self, String> {
+        let where_query = WhereExpr::from_options(&option)?
+            .map(|where_expr| where_expr.to_es_query())
+            .transpose()?;
+        let message_search = MessageSearch::create(&option)?;
+        let message_query = message_search
+            .as_ref()
+            .map(|message_search| message_search.to_es_query())
+            .transpose()?;
+        let parsers = ParserRegistry::create(&option.parsers)?;
+        let routes = RouteNormalizer::create(&option.route_templates)?;
+        let timestamps = TimestampParser::create(&option)?;
         Ok(ESGetStrategy {
             index,
             reverse: option.reverse,
             limit: option.limit,
-            date_filter: option.date_filter,
-            date_filter_string: option.date_filter_string,
+            time_range: option.time_range,
             level_filter: option.level_filter,
+            status_filter: option.status_filter,
+            slower_than: option.slower_than,
+            field_filters: option.field_filters,
+            where_query,
+            message_query,
+            message_search,
+            parsers,
+            routes,
+            timestamps,
             es_client,
         })
     }
@@ -39,7 +80,7 @@ impl<'a> ESGetStrategy<'a> {
     fn search_body_builder(&self) -> serde_json::Value {
         let mut es_search_body = json!({"query": {},});
 
-        if self.reverse == true {
+        if self.reverse {
             es_search_body["sort"] = json!(   [
               {
                 "time_unix": {
@@ -50,27 +91,67 @@ impl<'a> ESGetStrategy<'a> {
         }
         let mut filter_values: Vec<Value> = Vec::new();
 
-        if let Some(date_filter) = self.date_filter {
-            trace!(
-                "filter_logs : date_string {}",
-                self.date_filter_string.clone().unwrap_or("-".to_string())
-            );
+        if let Some(time_range) = &self.time_range {
+            trace!("filter_logs : time range {}", time_range);
+            filter_values.push(time_range.to_es_query());
+        }
+        if let Some(levels) = &self.level_filter {
+            trace!("filter_logs : level {:?}", levels);
+            // saved in the lowercase serde form
+            let levels: Vec<String> = levels
+                .iter()
+                .map(|level| level.as_str().to_lowercase())
+                .collect();
             filter_values.push(json!({
-            "range":{
-                "time_unix": {
-                    "gte": date_filter.0,
-                    "lte": date_filter.1
+                "terms": {
+                    "level": levels
+                }
+            }));
+        }
+        if !self.status_filter.is_empty() {
+            trace!("filter_logs : status {:?}", self.status_filter);
+            let ranges: Vec<Value> = self
+                .status_filter
+                .iter()
+                .map(|(start, end)| {
+                    json!({
+                        "range": {
+                            "status_code": {"gte": start, "lte": end}
+                        }
+                    })
+                })
+                .collect();
+            filter_values.push(json!({
+                "bool": {
+                    "should": ranges,
+                    "minimum_should_match": 1
+                }
+            }));
+        }
+        if let Some(slower_than) = self.slower_than {
+            trace!("filter_logs : slower than {} ms", slower_than);
+            filter_values.push(json!({
+                "range": {
+                    "process_time": {"gt": slower_than}
                 }
-            }}));
+            }));
         }
-        if let Some(level) = &self.level_filter {
-            trace!("filter_logs : level {}", level.to_string());
-            filter_values.push(json!(            {
-                "term": {
-                    "level": level.to_string()
+        for (name, value) in &self.field_filters {
+            trace!("filter_logs : field {} = {}", name, value);
+            filter_values.push(json!({
+                "match_phrase": {
+                    name: value
                 }
             }));
         }
+        if let Some(where_query) = &self.where_query {
+            trace!("filter_logs : where {}", where_query);
+            filter_values.push(where_query.clone());
+        }
+        if let Some(message_query) = &self.message_query {
+            trace!("filter_logs : message {}", message_query);
+            filter_values.push(message_query.clone());
+        }
         if filter_values.len() == 1 {
             es_search_body["query"] = filter_values.first().unwrap().clone();
         } else if filter_values.len() > 1 {
@@ -91,47 +172,164 @@ impl<'a> ESGetStrategy<'a> {
 }
 
 impl<'a> GetLogTrait for ESGetStrategy<'a> {
-    fn get(&self) -> Result<Vec<LogEntry>, String> {
-        let es_search_body = self.search_body_builder();
+    fn get(&self) -> Result<LogStream<'_>, String> {
+        let limit = self.limit.max(0);
+        if limit == 0 {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let hits = EsScroll::start(
+            self.es_client,
+            &self.index,
+            self.search_body_builder(),
+            limit.min(PAGE_SIZE),
+        )?;
+        let logs: LogStream<'_> = Box::new(hits.map(|hit| {
+            let hit: ElasticsearchHit =
+                serde_json::from_value(hit?).map_err(|err| format!("{err}"))?;
+            let log_entry: LogEntry =
+                serde_json::from_value(hit._source).map_err(|err| format!("{err}"))?;
+            Ok(log_entry)
+        }));
+        let logs = process_logs(logs, &self.parsers, &self.routes, &self.timestamps);
+        // the limit is taken after the recheck, so hits it drops don't count and more pages
+        // are scrolled until enough logs match
+        let logs: LogStream<'_> = match self
+            .message_search
+            .as_ref()
+            .filter(|message_search| message_search.needs_recheck())
+        {
+            Some(message_search) => Box::new(logs.filter(move |log| match log {
+                Ok(log) => message_search.matches(log),
+                Err(_) => true,
+            })),
+            None => logs,
+        };
+
+        Ok(Box::new(logs.take(limit as usize)))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        self.timestamps.report();
+        Ok(())
+    }
+}
 
+/// Hits of a scrolled search, fetched a page at a time as they are pulled so only one page
+/// is held in memory. The scroll is cleared when the iterator is dropped.
+struct EsScroll<'a> {
+    es_client: &'a EsClient,
+    runtime: Runtime,
+    scroll_id: Option<String>,
+    hits: std::vec::IntoIter<Value>,
+    done: bool,
+}
+
+impl<'a> EsScroll<'a> {
+    /// Sends the search and reads the first page, so a failing query fails here.
+    fn start(
+        es_client: &'a EsClient,
+        index: &str,
+        body: Value,
+        page_size: i64,
+    ) -> Result<Self, String> {
         let runtime =
             Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
-        let result_json: String = runtime.block_on(async {
-            let result = self
-                .es_client
+        let response = runtime.block_on(async {
+            es_client
                 .client
-                .search(SearchParts::Index(&[self.index.as_str()]))
-                .size(self.limit)
-                .body(es_search_body)
+                .search(SearchParts::Index(&[index]))
+                .scroll(SCROLL_KEEP_ALIVE)
+                .size(page_size)
+                .body(body)
                 .send()
                 .await
-                .map_err(|err| format!("Failed opening file: {}", err))?;
+                .map_err(|err| format!("Failed to search: {}", err))?
+                .json::<Value>()
+                .await
+                .map_err(|err| format!("Failed to parse response: {}", err))
+        })?;
+        let mut scroll = EsScroll {
+            es_client,
+            runtime,
+            scroll_id: None,
+            hits: Vec::new().into_iter(),
+            done: false,
+        };
+        scroll.read_page(response)?;
+        Ok(scroll)
+    }
 
-            result
-                .text()
+    fn next_page(&mut self) -> Result<(), String> {
+        let Some(scroll_id) = self.scroll_id.clone() else {
+            self.done = true;
+            return Ok(());
+        };
+        let es_client = self.es_client;
+        let response = self.runtime.block_on(async {
+            es_client
+                .client
+                .scroll(ScrollParts::None)
+                .body(json!({"scroll": SCROLL_KEEP_ALIVE, "scroll_id": scroll_id}))
+                .send()
+                .await
+                .map_err(|err| format!("Failed to scroll: {}", err))?
+                .json::<Value>()
                 .await
-                .map_err(|err| format!("Failed to get response text: {}", err))
+                .map_err(|err| format!("Failed to parse response: {}", err))
         })?;
+        self.read_page(response)
+    }
 
-        let response: serde_json::Value = serde_json::from_str(&result_json).unwrap();
-
-        let logs: Result<Vec<LogEntry>, String> = response["hits"]["hits"]
-            .as_array()
-            .ok_or_else(|| "Failed to get hits array".into())
-            .and_then(|hits| {
-                hits.iter()
-                    .map(|hit| {
-                        let hit: ElasticsearchHit =
-                            serde_json::from_value(hit.clone()).map_err(|err| format!("{err}"))?;
-                        let log_entry: LogEntry = serde_json::from_value(hit._source.clone())
-                            .map_err(|err| format!("{err}"))?;
-                        Ok(log_entry)
-                    })
-                    .collect()
-            });
-        let logs = logs?;
+    fn read_page(&mut self, mut response: Value) -> Result<(), String> {
+        if let Value::String(scroll_id) = response["_scroll_id"].take() {
+            self.scroll_id = Some(scroll_id);
+        }
+        let hits = match response["hits"]["hits"].take() {
+            Value::Array(hits) => hits,
+            _ => return Err(format!("Failed to get hits array: {}", response["error"])),
+        };
+        debug!("es_get: page of {} hits", hits.len());
+        self.done = hits.is_empty();
+        self.hits = hits.into_iter();
+        Ok(())
+    }
+}
+
+impl Iterator for EsScroll<'_> {
+    type Item = Result<Value, String>;
 
-        Ok(logs)
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(hit) = self.hits.next() {
+                return Some(Ok(hit));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.next_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl Drop for EsScroll<'_> {
+    fn drop(&mut self) {
+        let Some(scroll_id) = self.scroll_id.take() else {
+            return;
+        };
+        let es_client = self.es_client;
+        let cleared = self.runtime.block_on(
+            es_client
+                .client
+                .clear_scroll(ClearScrollParts::None)
+                .body(json!({"scroll_id": [scroll_id]}))
+                .send(),
+        );
+        if let Err(err) = cleared {
+            debug!("es_get: failed to clear scroll: {}", err);
+        }
     }
 }
 
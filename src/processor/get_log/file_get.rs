@@ -1,147 +1,142 @@
-use log::trace;
-use std::{fs, io::Read, path::PathBuf};
+use log::{debug, trace};
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::processor::{
-    log_entry::{LogEntry, LogLevel},
+    decode_log::{
+        compression::{open_reader, STDIN_PATH},
+        log_decoder::{decode_logs, DecodeOptions},
+    },
+    get_log::follow_reader::FollowReader,
+    log_filter::LogFilter,
+    log_parser::ParserRegistry,
     log_processor_options::LogProcessorOptions,
-    log_trait::GetLogTrait,
+    log_stream::{filter_logs, limit_logs, merge_logs, process_logs, process_logs_date},
+    log_trait::{GetLogTrait, LogStream},
+    route::RouteNormalizer,
+    timestamp::TimestampParser,
 };
 
 pub struct FileGetStrategy {
-    path: PathBuf,
+    paths: Vec<PathBuf>,
+    decode: DecodeOptions,
     reverse: bool,
     limit: i64,
-    level_filter: Option<LogLevel>,
-    date_filter_string: Option<String>,
-    date_filter: Option<(i64, i64)>,
+    with_source_file: bool,
+    follow: bool,
+    poll_interval: Duration,
+    filter: LogFilter,
+    parsers: ParserRegistry,
+    routes: RouteNormalizer,
+    timestamps: TimestampParser,
 }
 impl FileGetStrategy {
-    pub fn create(path: PathBuf, option: LogProcessorOptions) -> Result<Self, String> {
+    pub fn create(paths: Vec<PathBuf>, option: LogProcessorOptions) -> Result<Self, String> {
+        let paths = expand_paths(&paths)?;
+        if option.follow && paths.len() != 1 {
+            return Err("--follow needs exactly one input file".to_string());
+        }
         Ok(FileGetStrategy {
-            path,
+            paths,
+            decode: DecodeOptions::create(&option)?,
             reverse: option.reverse,
             limit: option.limit,
-            date_filter: option.date_filter,
-            date_filter_string: option.date_filter_string,
-            level_filter: option.level_filter,
+            with_source_file: option.with_source_file,
+            follow: option.follow,
+            poll_interval: option.poll_interval,
+            filter: LogFilter::create(&option)?,
+            parsers: ParserRegistry::create(&option.parsers)?,
+            routes: RouteNormalizer::create(&option.route_templates)?,
+            timestamps: TimestampParser::create(&option)?,
         })
     }
 }
 
 impl FileGetStrategy {
-    fn read_file_contents(&self) -> Result<Vec<LogEntry>, String> {
-        trace!("read_file_contents");
-        let mut file = fs::OpenOptions::new()
-            .read(true)
-            .open(&self.path)
-            .map_err(|err| format!("Failed opening file: {}", err))?;
-
-        let mut body = String::new();
-        file.read_to_string(&mut body)
-            .map_err(|err| format!("Failed reading file: {}", err))?;
-
-        let logs: Vec<LogEntry> =
-            serde_json::from_str(&body).map_err(|err| format!("Failed parsing file: {}", err))?;
-        Ok(logs)
-    }
-    fn process_logs_date(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("process_logs_date");
-        let mut i = 0;
-        let len = logs.len();
-        for log in &mut logs {
-            log.process_date();
-
-            if i % 1000 == 0 {
-                trace!("process_logs_date: {} /{}", i, len);
-            }
-            i = i + 1;
-        }
-        trace!("process_logs finished");
-
-        Ok(logs)
-    }
-
-    fn process_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("process_logs");
-        let mut i = 0;
-        let len = logs.len();
-        for log in &mut logs {
-            log.process();
-            if i % 1000 == 0 {
-                trace!("process_logs: {} /{}", i, len);
-            }
-            i = i + 1;
-        }
-        trace!("process_logs finished");
-
-        Ok(logs)
-    }
-
-    fn filter_logs_date(&self, logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if let Some(date_filter) = self.date_filter {
-            trace!("filter_logs_date");
-            trace!(
-                " date_string {}",
-                self.date_filter_string.clone().unwrap_or("-".to_string())
-            );
-            let filtered_logs = logs
-                .into_iter()
-                .filter(|log| {
-                    if let Some(time_unix) = log.time_unix {
-                        &time_unix > &date_filter.0 && &time_unix < &date_filter.1
-                    } else {
-                        false
-                    }
-                })
-                .collect();
-            Ok(filtered_logs)
+    fn read_file_contents(&self, path: &Path) -> Result<LogStream<'static>, String> {
+        trace!("read_file_contents {}", path.display());
+        let reader: Box<dyn BufRead> = if self.follow && path.as_os_str() != STDIN_PATH {
+            let reader = FollowReader::open(path, self.poll_interval, self.decode.idle_timeout())?;
+            Box::new(BufReader::new(reader))
         } else {
-            trace!("skip filter");
-            Ok(logs)
+            open_reader(path)?
+        };
+        let logs = decode_logs(reader, &self.decode)?;
+
+        if !self.with_source_file {
+            return Ok(logs);
         }
-    }
-    fn filter_logs_level(&self, logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if let Some(level) = &self.level_filter {
-            trace!("filter_logs_level");
-            trace!(" level {:?}", level);
-            trace!("logs :{}", logs.len());
-            let filtered_logs: Vec<LogEntry> =
-                logs.into_iter().filter(|log| &log.level == level).collect();
-            trace!("logs :{}", filtered_logs.len());
-            Ok(filtered_logs)
+        let source_file = if path.as_os_str() == STDIN_PATH {
+            "stdin".to_string()
         } else {
-            trace!("skip filter");
-            Ok(logs)
-        }
+            path.display().to_string()
+        };
+        Ok(Box::new(logs.map(move |log| {
+            log.map(|mut log| {
+                log.source_file = Some(source_file.clone());
+                log
+            })
+        })))
     }
+}
 
-    fn reverse_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if self.reverse {
-            trace!("reverse_logs");
-            logs.reverse();
-        } else {
-            trace!("skip reverse_logs");
-        }
+impl GetLogTrait for FileGetStrategy {
+    fn get(&self) -> Result<LogStream<'_>, String> {
+        let logs = self
+            .paths
+            .iter()
+            .map(|path| {
+                self.read_file_contents(path)
+                    .map(|logs| process_logs_date(logs, &self.timestamps))
+            })
+            .collect::<Result<Vec<LogStream>, String>>()?;
+        let logs = merge_logs(logs);
+        let logs = process_logs(logs, &self.parsers, &self.routes, &self.timestamps);
+        let logs = filter_logs(logs, &self.filter);
+        let logs = limit_logs(logs, self.reverse, self.limit);
         Ok(logs)
     }
 
-    fn limit_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("limit_logs {}", self.limit);
-
-        logs.truncate(self.limit as usize);
-        Ok(logs)
+    fn finish(&self) -> Result<(), String> {
+        self.timestamps.report();
+        Ok(())
     }
 }
 
-impl GetLogTrait for FileGetStrategy {
-    fn get(&self) -> Result<Vec<LogEntry>, String> {
-        let logs = self.read_file_contents()?;
-        let logs = self.process_logs_date(logs)?;
-        let logs = self.filter_logs_level(logs)?;
-        let logs = self.filter_logs_date(logs)?;
-        let logs = self.reverse_logs(logs)?;
-        let logs = self.limit_logs(logs)?;
-        let logs = self.process_logs(logs)?;
-        Ok(logs)
+/// Expands directories into the files they contain and glob patterns into their matches.
+fn expand_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut expanded: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let path_string = path.to_string_lossy();
+        if path_string == STDIN_PATH {
+            expanded.push(path.clone());
+        } else if path.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|err| format!("Failed reading directory {}: {}", path_string, err))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect();
+            files.sort();
+            expanded.extend(files);
+        } else if path_string.contains(['*', '?', '[']) {
+            let matches = glob::glob(&path_string)
+                .map_err(|err| format!("Failed parsing glob {}: {}", path_string, err))?;
+            let files: Vec<PathBuf> = matches
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .collect();
+            if files.is_empty() {
+                return Err(format!("No file matches {}", path_string));
+            }
+            expanded.extend(files);
+        } else {
+            expanded.push(path.clone());
+        }
     }
+    debug!("input files: {:?}", expanded);
+    Ok(expanded)
 }
@@ -0,0 +1,169 @@
+use log::{debug, trace};
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+/// Blocking `Read` that behaves like `tail -F`: it starts at the end of the file, waits for
+/// new data instead of returning end of file, reopens the path when the file is rotated and
+/// rewinds when it is truncated. With an idle timeout, reads fail with `TimedOut` each time
+/// no data arrived for that long.
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    file_id: u64,
+    position: u64,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+    idle: Duration,
+}
+
+impl FollowReader {
+    pub fn open(
+        path: &Path,
+        poll_interval: Duration,
+        idle_timeout: Option<Duration>,
+    ) -> Result<Self, String> {
+        let mut file = File::open(path).map_err(|err| format!("Failed opening file: {}", err))?;
+        let metadata = file
+            .metadata()
+            .map_err(|err| format!("Failed reading file: {}", err))?;
+        let position = file
+            .seek(SeekFrom::End(0))
+            .map_err(|err| format!("Failed reading file: {}", err))?;
+        Ok(FollowReader {
+            path: path.to_path_buf(),
+            file,
+            file_id: file_id(&metadata),
+            position,
+            poll_interval,
+            idle_timeout,
+            idle: Duration::ZERO,
+        })
+    }
+
+    /// Called at end of file, switches to the new file after a rotation or rewinds after a
+    /// truncation.
+    fn check_rotation(&mut self) -> io::Result<()> {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            // moved away and not recreated yet
+            return Ok(());
+        };
+        if file_id(&metadata) != self.file_id {
+            debug!("follow: {} rotated", self.path.display());
+            self.file = File::open(&self.path)?;
+            self.file_id = file_id(&metadata);
+            self.position = 0;
+        } else if metadata.len() < self.position {
+            debug!("follow: {} truncated", self.path.display());
+            self.position = self.file.seek(SeekFrom::Start(0))?;
+        }
+        Ok(())
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.file.read(buf)?;
+            if len > 0 {
+                self.position += len as u64;
+                self.idle = Duration::ZERO;
+                return Ok(len);
+            }
+            self.check_rotation()?;
+            if self
+                .idle_timeout
+                .is_some_and(|timeout| self.idle >= timeout)
+            {
+                self.idle = Duration::ZERO;
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no new data"));
+            }
+            wait_for_more(self.poll_interval);
+            self.idle += self.poll_interval;
+        }
+    }
+}
+
+/// Sleeps until the next poll. Stdout is flushed first so entries printed so far are visible
+/// while the input is idle.
+pub fn wait_for_more(poll_interval: Duration) {
+    trace!("follow: waiting {:?}", poll_interval);
+    io::stdout().flush().ok();
+    thread::sleep(poll_interval);
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLL: Duration = Duration::from_millis(10);
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("salog-follow-{}-{}.log", std::process::id(), name))
+    }
+
+    fn read_some(reader: &mut FollowReader) -> io::Result<String> {
+        let mut buf = [0u8; 64];
+        let len = reader.read(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf[..len]).to_string())
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_follow_appended_and_truncated() {
+        let path = temp_path("truncate");
+        fs::write(&path, "old line\n").unwrap();
+        let mut reader = FollowReader::open(&path, POLL, Some(POLL * 5)).unwrap();
+
+        append(&path, "new line\n");
+        assert_eq!(read_some(&mut reader).unwrap(), "new line\n");
+        let timed_out = read_some(&mut reader).unwrap_err();
+        assert_eq!(timed_out.kind(), io::ErrorKind::TimedOut);
+
+        fs::write(&path, "short\n").unwrap();
+        assert_eq!(read_some(&mut reader).unwrap(), "short\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotated() {
+        let path = temp_path("rotate");
+        let rotated = temp_path("rotate.1");
+        fs::write(&path, "old line\n").unwrap();
+        let mut reader = FollowReader::open(&path, POLL, Some(POLL * 5)).unwrap();
+
+        append(&path, "last line\n");
+        fs::rename(&path, &rotated).unwrap();
+        fs::write(&path, "first line of the new file\n").unwrap();
+        assert_eq!(read_some(&mut reader).unwrap(), "last line\n");
+        assert_eq!(
+            read_some(&mut reader).unwrap(),
+            "first line of the new file\n"
+        );
+
+        append(&path, "more\n");
+        assert_eq!(read_some(&mut reader).unwrap(), "more\n");
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -1,156 +1,209 @@
 use log::{debug, trace};
+use std::{
+    collections::VecDeque,
+    io::{self, BufReader, Read},
+    time::Duration,
+};
 use tokio::runtime::Runtime;
 
 use crate::processor::{
-    log_entry::{LogEntry, LogLevel},
+    decode_log::log_decoder::{decode_line, decode_logs, DecodeOptions},
+    get_log::follow_reader::wait_for_more,
+    log_entry::LogEntry,
+    log_filter::LogFilter,
+    log_parser::ParserRegistry,
     log_processor_options::LogProcessorOptions,
-    log_trait::GetLogTrait,
+    log_stream::{filter_logs, limit_logs, process_logs},
+    log_trait::{GetLogTrait, LogStream},
+    route::RouteNormalizer,
+    timestamp::TimestampParser,
 };
 
 pub struct UrlGetStrategy {
     url: String,
+    decode: DecodeOptions,
     reverse: bool,
     limit: i64,
-    date_filter_string: Option<String>,
-    date_filter: Option<(i64, i64)>,
-    level_filter: Option<LogLevel>,
+    follow: bool,
+    poll_interval: Duration,
+    filter: LogFilter,
+    parsers: ParserRegistry,
+    routes: RouteNormalizer,
+    timestamps: TimestampParser,
 }
 impl UrlGetStrategy {
     pub fn create(url: String, option: LogProcessorOptions) -> Result<Self, String> {
         Ok(UrlGetStrategy {
             url,
+            decode: DecodeOptions::create(&option)?,
             reverse: option.reverse,
             limit: option.limit,
-            date_filter: option.date_filter,
-            date_filter_string: option.date_filter_string,
-            level_filter: option.level_filter,
+            follow: option.follow,
+            poll_interval: option.poll_interval,
+            filter: LogFilter::create(&option)?,
+            parsers: ParserRegistry::create(&option.parsers)?,
+            routes: RouteNormalizer::create(&option.route_templates)?,
+            timestamps: TimestampParser::create(&option)?,
         })
     }
 }
 
 impl UrlGetStrategy {
-    async fn parse_data_from_url(&self) -> Result<Vec<LogEntry>, String> {
+    fn parse_data_from_url(&self) -> Result<LogStream<'static>, String> {
         trace!("parse_data_from_url");
         let url = get_default_url_from_env(&self.url)?;
         debug!("url: {}", url);
-        // Make the HTTP request
-        let response = reqwest::get(url)
-            .await
-            .map_err(|err| format!("failed fetching response : {}", err))?;
-        let body: String = response
-            .text()
-            .await
+        let runtime =
+            Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
+        // Make the HTTP request, the body is read chunk by chunk while iterating
+        let response = runtime
+            .block_on(reqwest::get(url))
             .map_err(|err| format!("failed fetching response : {}", err))?;
-        debug!("url body: {}", body.len());
-        // Parse each line as JSON and collect into Vec<Log>
-        let logs: Vec<LogEntry> = body
-            .lines()
-            .filter_map(|line| serde_json::from_str(line).unwrap())
-            .collect();
-        debug!("logs: {}", logs.len());
-        Ok(logs)
-    }
-    fn process_logs_date(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("process_logs_date");
-        let mut i = 0;
-        let len = logs.len();
-        for log in &mut logs {
-            log.process_date();
-
-            if i % 1000 == 0 {
-                trace!("process_logs_date: {} /{}", i, len);
-            }
-            i = i + 1;
-        }
-        trace!("process_logs finished");
+        let body = BufReader::new(ResponseReader {
+            runtime,
+            response,
+            chunk: Vec::new(),
+            position: 0,
+        });
 
-        Ok(logs)
+        decode_logs(Box::new(body), &self.decode)
     }
 
-    fn process_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("process_logs");
-        let mut i = 0;
-        let len = logs.len();
-        for log in &mut logs {
-            log.process();
-            if i % 1000 == 0 {
-                trace!("process_logs: {} /{}", i, len);
+    /// Polls the url every `poll_interval` and yields the lines appended since the previous
+    /// response. The first response only moves the cursor to its end.
+    fn follow_url(&self) -> Result<LogStream<'static>, String> {
+        trace!("follow_url");
+        let url = get_default_url_from_env(&self.url)?;
+        debug!("url: {}", url);
+        let runtime =
+            Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
+        let poll_interval = self.poll_interval;
+        let decode = self.decode.clone();
+
+        let mut cursor: Option<BodyCursor> = None;
+        let mut pending: VecDeque<Result<LogEntry, String>> = VecDeque::new();
+        Ok(Box::new(std::iter::from_fn(move || loop {
+            if let Some(log) = pending.pop_front() {
+                return Some(log);
+            }
+            if cursor.is_some() {
+                wait_for_more(poll_interval);
             }
-            i = i + 1;
-        }
-        trace!("process_logs finished");
 
-        Ok(logs)
+            let body = match runtime.block_on(fetch_body(&url)) {
+                Ok(body) => body,
+                Err(err) => return Some(Err(err)),
+            };
+            match &mut cursor {
+                Some(cursor) => {
+                    for line in cursor.new_lines(&body) {
+                        pending.push_back(decode_line(line, &decode));
+                    }
+                }
+                None => cursor = Some(BodyCursor::at_end(&body)),
+            }
+            debug!("follow_url: {} new lines", pending.len());
+        })))
     }
+}
 
-    fn filter_logs_date(&self, logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if let Some(date_filter) = self.date_filter {
-            trace!("filter_logs_date");
-            trace!(
-                " date_string {}",
-                self.date_filter_string.clone().unwrap_or("-".to_string())
-            );
-            let filtered_logs = logs
-                .into_iter()
-                .filter(|log| {
-                    if let Some(time_unix) = log.time_unix {
-                        &time_unix > &date_filter.0 && &time_unix < &date_filter.1
-                    } else {
-                        false
-                    }
-                })
-                .collect();
-            Ok(filtered_logs)
-        } else {
-            trace!("skip filter");
-            Ok(logs)
-        }
+/// Position in a polled body after the last complete line that was read, like the offset
+/// of the file follower. The line before it is kept to notice when the body was replaced
+/// rather than appended to, e.g. after a rotation, and start again from the beginning.
+#[derive(Debug, Default)]
+struct BodyCursor {
+    position: usize,
+    last_line: String,
+}
+
+impl BodyCursor {
+    fn at_end(body: &str) -> Self {
+        let mut cursor = BodyCursor::default();
+        cursor.new_lines(body);
+        cursor
     }
-    fn filter_logs_level(&self, logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if let Some(level) = &self.level_filter {
-            trace!("filter_logs_level");
-            trace!(" level {:?}", level);
-            trace!("logs :{}", logs.len());
-            let filtered_logs: Vec<LogEntry> =
-                logs.into_iter().filter(|log| &log.level == level).collect();
-            trace!("logs :{}", filtered_logs.len());
-            Ok(filtered_logs)
-        } else {
-            trace!("skip filter");
-            Ok(logs)
+
+    /// The complete lines after the cursor, moving it past them. A last line without a
+    /// newline is left for the next poll, it may still be written.
+    fn new_lines<'a>(&mut self, body: &'a str) -> Vec<&'a str> {
+        let appended = body.len() >= self.position
+            && body.is_char_boundary(self.position)
+            && body[..self.position].ends_with(&self.last_line);
+        if !appended {
+            debug!("follow_url: response was replaced, reading it from the start");
+            *self = BodyCursor::default();
         }
+        let Some(end) = body[self.position..].rfind('\n') else {
+            return Vec::new();
+        };
+        let end = self.position + end + 1;
+        let lines = body[self.position..end]
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let last_start = body[..end - 1].rfind('\n').map_or(0, |start| start + 1);
+        self.last_line = body[last_start..end].to_string();
+        self.position = end;
+        lines
     }
+}
 
-    fn reverse_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        if self.reverse {
-            trace!("reverse_logs");
-            logs.reverse();
+async fn fetch_body(url: &str) -> Result<String, String> {
+    let response = reqwest::get(url)
+        .await
+        .map_err(|err| format!("failed fetching response : {}", err))?;
+    response
+        .text()
+        .await
+        .map_err(|err| format!("failed fetching response : {}", err))
+}
+
+impl GetLogTrait for UrlGetStrategy {
+    fn get(&self) -> Result<LogStream<'_>, String> {
+        let logs = if self.follow {
+            self.follow_url()?
         } else {
-            trace!("skip reverse_logs");
-        }
+            self.parse_data_from_url()?
+        };
+        let logs = process_logs(logs, &self.parsers, &self.routes, &self.timestamps);
+        let logs = filter_logs(logs, &self.filter);
+        let logs = limit_logs(logs, self.reverse, self.limit);
         Ok(logs)
     }
 
-    fn limit_logs(&self, mut logs: Vec<LogEntry>) -> Result<Vec<LogEntry>, String> {
-        trace!("limit_logs {}", self.limit);
-
-        logs.truncate(self.limit as usize);
-        Ok(logs)
+    fn finish(&self) -> Result<(), String> {
+        self.timestamps.report();
+        Ok(())
     }
 }
 
-impl GetLogTrait for UrlGetStrategy {
-    fn get(&self) -> Result<Vec<LogEntry>, String> {
-        let runtime =
-            Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
-        let logs = runtime.block_on(self.parse_data_from_url())?;
-        let logs = self.process_logs_date(logs)?;
-        let logs = self.filter_logs_level(logs)?;
-        let logs = self.filter_logs_date(logs)?;
-        let logs = self.reverse_logs(logs)?;
-        let logs = self.limit_logs(logs)?;
-        let logs = self.process_logs(logs)?;
-        Ok(logs)
+/// Blocking `Read` over the body of an async response.
+struct ResponseReader {
+    runtime: Runtime,
+    response: reqwest::Response,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ResponseReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            let chunk = self
+                .runtime
+                .block_on(self.response.chunk())
+                .map_err(io::Error::other)?;
+            match chunk {
+                Some(chunk) => {
+                    self.chunk = chunk.to_vec();
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
     }
 }
 
@@ -166,3 +219,21 @@ fn get_default_url_from_env(suffix: &str) -> Result<String, String> {
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_cursor() {
+        let mut cursor = BodyCursor::at_end("a\nb\n");
+        assert!(cursor.new_lines("a\nb\n").is_empty());
+        // a repeated line is new, a partial line waits for its newline
+        assert_eq!(cursor.new_lines("a\nb\nb\nc"), ["b"]);
+        assert_eq!(cursor.new_lines("a\nb\nb\nc\r\n\nd\n"), ["c", "d"]);
+        assert!(cursor.new_lines("a\nb\nb\nc\r\n\nd\n").is_empty());
+        // replaced by a new body
+        assert_eq!(cursor.new_lines("e\n"), ["e"]);
+        assert_eq!(cursor.new_lines("x\ny\n"), ["x", "y"]);
+    }
+}
@@ -0,0 +1,229 @@
+use log::trace;
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::OnceLock,
+};
+
+/// Subset of the Logstash grok-patterns library, written for the `regex` crate which has no
+/// look-around.
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    (
+        "EMAILLOCALPART",
+        r"[a-zA-Z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-zA-Z0-9!#$%&'*+/=?^_`{|}~-]+)*",
+    ),
+    ("EMAILADDRESS", r"%{EMAILLOCALPART}@%{HOSTNAME}"),
+    ("INT", r"(?:[+-]?(?:[0-9]+))"),
+    ("BASE10NUM", r"(?:[+-]?(?:[0-9]+(?:\.[0-9]+)?|\.[0-9]+))"),
+    ("NUMBER", r"(?:%{BASE10NUM})"),
+    ("BASE16NUM", r"(?:0[xX])?[0-9A-Fa-f]+"),
+    ("POSINT", r"\b(?:[1-9][0-9]*)\b"),
+    ("NONNEGINT", r"\b(?:[0-9]+)\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#"(?:"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*')"#),
+    ("QS", r"%{QUOTEDSTRING}"),
+    (
+        "UUID",
+        r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}",
+    ),
+    ("MAC", r"(?:[A-Fa-f0-9]{2}[:-]){5}[A-Fa-f0-9]{2}"),
+    (
+        "IPV4",
+        r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)",
+    ),
+    (
+        "IPV6",
+        r"(?:[0-9A-Fa-f]{0,4}:){2,7}(?:%{IPV4}|[0-9A-Fa-f]{0,4})(?:%[0-9A-Za-z]+)?",
+    ),
+    ("IP", r"(?:%{IPV6}|%{IPV4})"),
+    (
+        "HOSTNAME",
+        r"\b(?:[0-9A-Za-z][0-9A-Za-z-]{0,62})(?:\.(?:[0-9A-Za-z][0-9A-Za-z-]{0,62}))*\.?",
+    ),
+    ("IPORHOST", r"(?:%{IP}|%{HOSTNAME})"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    ("UNIXPATH", r"(?:/[\w_%!$@:.,+~-]*)+"),
+    ("WINPATH", r"(?:[A-Za-z]+:|\\)(?:\\[^\\?*]*)+"),
+    ("PATH", r"(?:%{UNIXPATH}|%{WINPATH})"),
+    ("URIPROTO", r"[A-Za-z][A-Za-z0-9+\-.]+"),
+    ("URIHOST", r"%{IPORHOST}(?::%{POSINT})?"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("URIPARAM", r"\?[A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*"),
+    ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+    (
+        "URI",
+        r"%{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?(?:%{URIHOST})?(?:%{URIPATHPARAM})?",
+    ),
+    (
+        "MONTH",
+        r"\b(?:[Jj]an(?:uary)?|[Ff]eb(?:ruary)?|[Mm]ar(?:ch)?|[Aa]pr(?:il)?|[Mm]ay|[Jj]un(?:e)?|[Jj]ul(?:y)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo]ct(?:ober)?|[Nn]ov(?:ember)?|[Dd]ec(?:ember)?)\b",
+    ),
+    ("MONTHNUM", r"(?:0?[1-9]|1[0-2])"),
+    ("MONTHDAY", r"(?:(?:0[1-9])|(?:[12][0-9])|(?:3[01])|[1-9])"),
+    (
+        "DAY",
+        r"(?:Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?)",
+    ),
+    ("YEAR", r"(?:\d\d){1,2}"),
+    ("HOUR", r"(?:2[0123]|[01]?[0-9])"),
+    ("MINUTE", r"(?:[0-5][0-9])"),
+    ("SECOND", r"(?:(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?)"),
+    ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+    ("ISO8601_TIMEZONE", r"(?:Z|[+-]%{HOUR}(?::?%{MINUTE}))"),
+    (
+        "TIMESTAMP_ISO8601",
+        r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?",
+    ),
+    ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    (
+        "LOGLEVEL",
+        r"(?:[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo|INFO|[Ww]arn(?:ing)?|WARN(?:ING)?|[Ee]rr(?:or)?|ERR(?:OR)?|[Cc]rit(?:ical)?|CRIT(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|[Ee]merg(?:ency)?|EMERG(?:ENCY)?)",
+    ),
+];
+
+/// Nesting limit for pattern references, reached only by self-referencing patterns.
+const MAX_DEPTH: usize = 32;
+
+/// Returns true when `pattern` uses `%{NAME}` references and must be compiled as grok.
+pub fn is_grok(pattern: &str) -> bool {
+    pattern.contains("%{")
+}
+
+fn grok_reference_regex() -> &'static Regex {
+    static GROK_REFERENCE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    GROK_REFERENCE_REGEX.get_or_init(|| Regex::new(r"%\{(\w+)(?::([^:}]+))?(?::\w+)?\}").unwrap())
+}
+
+/// Named grok patterns, the built-in library plus any loaded from pattern files.
+pub struct GrokLibrary {
+    patterns: HashMap<String, String>,
+}
+
+impl GrokLibrary {
+    pub fn create() -> Self {
+        GrokLibrary {
+            patterns: BUILTIN_PATTERNS
+                .iter()
+                .map(|(name, pattern)| (name.to_string(), pattern.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn add_pattern(&mut self, name: &str, pattern: &str) {
+        self.patterns.insert(name.to_string(), pattern.to_string());
+    }
+
+    /// Loads a Logstash patterns file, one `NAME PATTERN` per line and `#` for comments.
+    pub fn add_patterns_file(&mut self, path: &Path) -> Result<(), String> {
+        let body = fs::read_to_string(path)
+            .map_err(|err| format!("Failed reading grok patterns: {}", err))?;
+        for line in body.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                Some((name, pattern)) => self.add_pattern(name, pattern.trim()),
+                None => return Err(format!("Failed parsing grok pattern line `{}`", line)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles a grok expression into a regex pattern. `%{NAME:field}` becomes the named
+    /// group `field`, which the parser copies onto the log field of the same name.
+    pub fn compile(&self, expression: &str) -> Result<String, String> {
+        let mut fields: HashSet<String> = HashSet::new();
+        let pattern = self.expand(expression, 0, &mut fields)?;
+        trace!("grok {} => {}", expression, pattern);
+        Ok(pattern)
+    }
+
+    fn expand(
+        &self,
+        expression: &str,
+        depth: usize,
+        fields: &mut HashSet<String>,
+    ) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("Grok pattern nested too deep at `{}`", expression));
+        }
+        let mut pattern = String::with_capacity(expression.len());
+        let mut last = 0;
+        for captures in grok_reference_regex().captures_iter(expression) {
+            let reference = captures.get(0).unwrap();
+            let name = &captures[1];
+            let definition = self
+                .patterns
+                .get(name)
+                .ok_or_else(|| format!("Unknown grok pattern %{{{}}}", name))?;
+            let expanded = self.expand(definition, depth + 1, fields)?;
+
+            pattern.push_str(&expression[last..reference.start()]);
+            // only the expression given by the user captures, and each field only once
+            let field = captures
+                .get(2)
+                .filter(|_| depth == 0)
+                .map(|field| field_name(field.as_str()))
+                .filter(|field| fields.insert(field.clone()));
+            match field {
+                Some(field) => pattern.push_str(&format!("(?P<{}>{})", field, expanded)),
+                None => pattern.push_str(&format!("(?:{})", expanded)),
+            }
+            last = reference.end();
+        }
+        pattern.push_str(&expression[last..]);
+        Ok(pattern)
+    }
+}
+
+/// Turns Logstash field references like `[client][ip]` or `client.ip` into `client_ip`.
+fn field_name(field: &str) -> String {
+    field
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .replace("][", "_")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_grok() {
+        let library = GrokLibrary::create();
+        let pattern = library
+            .compile(
+                "%{IP:ip_address} - %{WORD:http_method} %{URIPATH:url} %{NUMBER:[http][bytes]:int}",
+            )
+            .unwrap();
+        let regex = Regex::new(&pattern).unwrap();
+        let captures = regex.captures("10.1.2.3 - GET /api/users 512").unwrap();
+        assert_eq!(&captures["ip_address"], "10.1.2.3");
+        assert_eq!(&captures["http_method"], "GET");
+        assert_eq!(&captures["url"], "/api/users");
+        assert_eq!(&captures["http_bytes"], "512");
+    }
+
+    #[test]
+    fn test_compile_grok_unknown_and_custom_patterns() {
+        let mut library = GrokLibrary::create();
+        assert!(library.compile("%{NOPE:x}").is_err());
+
+        library.add_pattern("ORDER_ID", r"ORD-%{INT}");
+        let regex = Regex::new(&library.compile("order %{ORDER_ID:order}").unwrap()).unwrap();
+        assert_eq!(&regex.captures("order ORD-42").unwrap()["order"], "ORD-42");
+    }
+}
@@ -1,11 +1,13 @@
-use std::{fmt, sync::OnceLock};
+use std::{collections::BTreeMap, fmt};
 
-use chrono::{DateTime, Utc};
-use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_inline_default::serde_inline_default;
+use serde_json::Value;
 use strum::EnumString;
 
+use super::{log_parser::ParserRegistry, route::RouteNormalizer, timestamp::TimestampParser};
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize, EnumString, Clone, PartialEq, Eq, Hash)]
 pub enum HTTPMethod {
     GET,
@@ -20,6 +22,7 @@ pub enum HTTPMethod {
     NONE,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
@@ -33,6 +36,7 @@ pub enum LogLevel {
 #[serde_inline_default]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LogEntry {
+    #[serde(deserialize_with = "string_or_number")]
     pub timestamp: String,
     pub level: LogLevel,
     pub message: String,
@@ -46,8 +50,12 @@ pub struct LogEntry {
     #[serde(default)]
     pub url: String,
 
-    #[serde(default)]
-    pub status_code: String,
+    /// `url` normalized to its endpoint, e.g. `/users/:id`. Derived in `process`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub route: String,
+
+    #[serde(default, deserialize_with = "status_code_from_any")]
+    pub status_code: Option<u16>,
 
     #[serde(default)]
     pub error: String,
@@ -58,81 +66,150 @@ pub struct LogEntry {
     #[serde(default)]
     pub time_unix: Option<i64>,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+
+    /// Keys of the source log, and values captured by a parser, that have no field of their
+    /// own. Flattened so they are saved and output next to the regular fields.
+    #[serde(flatten)]
+    pub extra_fields: BTreeMap<String, Value>,
+
+    /// Only true in this run. Not saved, and read as false from logs saved by older
+    /// versions, so saved logs are processed again with the options of the run reading them.
+    #[serde(default, skip_serializing, deserialize_with = "not_processed")]
     is_process: bool,
 }
 
 impl LogEntry {
-    pub fn process_date(&mut self) {
+    /// Creates an entry holding only `message`, for decoders of text formats to fill in.
+    pub fn from_message(message: String) -> Self {
+        LogEntry {
+            timestamp: String::new(),
+            level: LogLevel::NONE,
+            message,
+            http_method: HTTPMethod::NONE,
+            ip_address: String::new(),
+            url: String::new(),
+            route: String::new(),
+            status_code: None,
+            error: String::new(),
+            process_time: 0f64,
+            time_unix: None,
+            source_file: None,
+            extra_fields: BTreeMap::new(),
+            is_process: false,
+        }
+    }
+
+    pub fn process_date(&mut self, timestamps: &TimestampParser) {
         if self.time_unix.is_none() {
-            let dt = self.timestamp.parse::<DateTime<Utc>>().ok();
-            if let Some(dt) = dt {
-                self.time_unix = Some(dt.timestamp_millis());
-            }
+            self.time_unix = timestamps.parse(&self.timestamp);
         }
     }
 
-    pub fn process(&mut self) {
+    pub fn process(
+        &mut self,
+        parsers: &ParserRegistry,
+        routes: &RouteNormalizer,
+        timestamps: &TimestampParser,
+    ) {
         if self.is_process {
             return;
         }
-        let regex = log_regex();
+        parsers.parse(self);
+        self.route = routes.normalize(&self.url);
 
-        if let Some(captures) = regex.captures(&self.message) {
-            self.ip_address = captures[1].to_string();
-            self.http_method = captures[2].parse().unwrap_or(HTTPMethod::NONE);
-            self.url = captures[3].to_string();
-            self.status_code = captures[4].to_string();
-            self.process_time = captures[5].parse().unwrap_or(0f64);
-        }
-
-        if self.time_unix.is_none() {
-            let dt = self.timestamp.parse::<DateTime<Utc>>().ok();
-            if let Some(dt) = dt {
-                self.time_unix = Some(dt.timestamp_millis());
-            }
-        }
+        self.process_date(timestamps);
+        timestamps.record(self);
 
         self.is_process = true;
     }
-}
 
-fn log_regex() -> &'static Regex {
-    // Define the regular expression pattern
-    static LOG_REGEX: OnceLock<Regex> = OnceLock::new();
+    /// Returns the text form of the field called `name`, looking in `extra_fields` when
+    /// `LogEntry` has no field with that name.
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "timestamp" => Some(self.timestamp.clone()),
+            "level" => Some(self.level.to_string()),
+            "message" => Some(self.message.clone()),
+            "http_method" => Some(self.http_method.to_string()),
+            "ip_address" => Some(self.ip_address.clone()),
+            "url" => Some(self.url.clone()),
+            "route" => Some(self.route.clone()),
+            "status_code" => self.status_code.map(|status_code| status_code.to_string()),
+            "error" => Some(self.error.clone()),
+            "process_time" => Some(self.process_time.to_string()),
+            "time_unix" => self.time_unix.map(|time_unix| time_unix.to_string()),
+            "source_file" => self.source_file.clone(),
+            _ => match self.extra_fields.get(name)? {
+                Value::Null => None,
+                Value::String(value) => Some(value.clone()),
+                value => Some(value.to_string()),
+            },
+        }
+    }
 
-    // Initialize the regular expression using OnceCell
-    LOG_REGEX.get_or_init(|| {
-        Regex::new(r"^([:\d\w.]+)\s-\s(\w+)\s([\/\w]+)\s(\d+)\s-\s([\d.]+).ms$").unwrap()
-    })
+    /// Sets the field called `name` from its text form, as captured by a parser. Returns
+    /// false when `LogEntry` has no field with that name.
+    pub fn set_field(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "timestamp" => {
+                self.timestamp = value.to_string();
+                self.time_unix = None;
+            }
+            "level" => self.level = LogLevel::from_name(value).unwrap_or(LogLevel::NONE),
+            "message" => self.message = value.to_string(),
+            "http_method" => {
+                self.http_method = value.to_uppercase().parse().unwrap_or(HTTPMethod::NONE)
+            }
+            "ip_address" => self.ip_address = value.to_string(),
+            "url" => self.url = value.to_string(),
+            "route" => self.route = value.to_string(),
+            "status_code" => self.status_code = value.trim().parse().ok(),
+            "error" => self.error = value.to_string(),
+            "process_time" => self.process_time = value.parse().unwrap_or(0f64),
+            "time_unix" => self.time_unix = value.parse().ok(),
+            _ => return false,
+        }
+        true
+    }
 }
 
 impl fmt::Display for LogEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{{\n")?;
-        write!(f, "  \"timestamp\": \"{}\",\n", self.timestamp)?;
-        write!(f, "  \"level\": \"{}\",\n", self.level)?;
-        write!(f, "  \"message\": \"{}\",\n", self.message)?;
+        writeln!(f, "{{")?;
+        writeln!(f, "  \"timestamp\": \"{}\",", self.timestamp)?;
+        writeln!(f, "  \"level\": \"{}\",", self.level)?;
+        writeln!(f, "  \"message\": \"{}\",", self.message)?;
 
-        write!(f, "  \"time_unix\": \"{}\",\n", self.time_unix.unwrap_or(0))?;
+        writeln!(f, "  \"time_unix\": \"{}\",", self.time_unix.unwrap_or(0))?;
 
         if self.http_method != HTTPMethod::NONE {
-            write!(f, "  \"http_method\": \"{}\",\n", self.http_method)?;
+            writeln!(f, "  \"http_method\": \"{}\",", self.http_method)?;
         }
         if !self.ip_address.is_empty() {
-            write!(f, "  \"ip_address\": \"{}\",\n", self.ip_address)?;
+            writeln!(f, "  \"ip_address\": \"{}\",", self.ip_address)?;
         }
         if !self.url.is_empty() {
-            write!(f, "  \"url\": \"{}\",\n", self.url)?;
+            writeln!(f, "  \"url\": \"{}\",", self.url)?;
         }
-        if !self.status_code.is_empty() {
-            write!(f, "  \"status_code\": \"{}\",\n", self.status_code)?;
+        if !self.route.is_empty() {
+            writeln!(f, "  \"route\": \"{}\",", self.route)?;
+        }
+        if let Some(status_code) = self.status_code {
+            writeln!(f, "  \"status_code\": {},", status_code)?;
         }
         if !self.error.is_empty() {
-            write!(f, "  \"error\": \"{}\",\n", self.error)?;
+            writeln!(f, "  \"error\": \"{}\",", self.error)?;
+        }
+        if let Some(source_file) = &self.source_file {
+            writeln!(f, "  \"source_file\": \"{}\",", source_file)?;
+        }
+        for (name, value) in &self.extra_fields {
+            writeln!(f, "  \"{}\": {},", name, value)?;
         }
 
-        write!(f, "  \"process_time\": {},\n", self.process_time)?;
+        writeln!(f, "  \"process_time\": {},", self.process_time)?;
 
         write!(f, "}}")
     }
@@ -140,41 +217,148 @@ impl fmt::Display for LogEntry {
 
 impl fmt::Display for LogLevel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string().as_str(),)
+        write!(f, "{}", self.as_str())
     }
 }
 
 impl std::fmt::Display for HTTPMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string().as_str(),)
+        write!(f, "{}", self.as_str())
     }
 }
 
 impl LogLevel {
-    fn to_string(&self) -> String {
+    /// Maps the level names used by common loggers onto `LogLevel`.
+    pub fn from_name(name: &str) -> Option<LogLevel> {
+        match name.trim().to_lowercase().as_str() {
+            "debug" | "trace" => Some(LogLevel::DEBUG),
+            "info" | "information" | "notice" => Some(LogLevel::INFO),
+            "warn" | "warning" => Some(LogLevel::WARN),
+            "error" | "err" | "fatal" | "critical" | "crit" => Some(LogLevel::ERROR),
+            "none" => Some(LogLevel::NONE),
+            _ => None,
+        }
+    }
+
+    /// Levels that have a severity, from least to most severe.
+    pub const SEVERITY_ORDER: [LogLevel; 4] = [
+        LogLevel::DEBUG,
+        LogLevel::INFO,
+        LogLevel::WARN,
+        LogLevel::ERROR,
+    ];
+
+    /// Position in `SEVERITY_ORDER`, `None` for `NONE`.
+    pub fn severity(&self) -> Option<usize> {
+        LogLevel::SEVERITY_ORDER
+            .iter()
+            .position(|level| level == self)
+    }
+
+    pub fn as_str(&self) -> &'static str {
         match self {
-            LogLevel::DEBUG => "DEBUG".to_string(),
-            LogLevel::ERROR => "ERROR".to_string(),
-            LogLevel::INFO => "INFO".to_string(),
-            LogLevel::WARN => "WARN".to_string(),
-            LogLevel::NONE => "none".to_string(),
+            LogLevel::DEBUG => "DEBUG",
+            LogLevel::ERROR => "ERROR",
+            LogLevel::INFO => "INFO",
+            LogLevel::WARN => "WARN",
+            LogLevel::NONE => "none",
         }
     }
 }
 
 impl HTTPMethod {
-    fn to_string(&self) -> String {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            HTTPMethod::GET => "GET".to_string(),
-            HTTPMethod::PUT => "PUT".to_string(),
-            HTTPMethod::POST => "POST".to_string(),
-            HTTPMethod::DELETE => "DELETE".to_string(),
-            HTTPMethod::PATCH => "PATCH".to_string(),
-            HTTPMethod::HEAD => "HEAD".to_string(),
-            HTTPMethod::OPTIONS => "OPTIONS".to_string(),
-            HTTPMethod::CONNECT => "CONNECT".to_string(),
-            HTTPMethod::TRACE => "TRACE".to_string(),
-            HTTPMethod::NONE => "none".to_string(),
+            HTTPMethod::GET => "GET",
+            HTTPMethod::PUT => "PUT",
+            HTTPMethod::POST => "POST",
+            HTTPMethod::DELETE => "DELETE",
+            HTTPMethod::PATCH => "PATCH",
+            HTTPMethod::HEAD => "HEAD",
+            HTTPMethod::OPTIONS => "OPTIONS",
+            HTTPMethod::CONNECT => "CONNECT",
+            HTTPMethod::TRACE => "TRACE",
+            HTTPMethod::NONE => "none",
         }
     }
 }
+
+/// Reads epoch timestamps written as JSON numbers into the text `timestamp` field.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(timestamp) => Ok(timestamp),
+        Value::Number(timestamp) => Ok(timestamp.to_string()),
+        Value::Null => Ok(String::new()),
+        value => Err(serde::de::Error::custom(format!(
+            "invalid timestamp {}, expected a string or a number",
+            value
+        ))),
+    }
+}
+
+fn not_processed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    serde::de::IgnoredAny::deserialize(deserializer)?;
+    Ok(false)
+}
+
+/// Reads the status code from a number, or from text as older saved logs have it. Text
+/// that is no status code, like `-` or an empty string, becomes `None`.
+fn status_code_from_any<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u16>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(status_code) => Ok(status_code
+            .as_u64()
+            .and_then(|status_code| u16::try_from(status_code).ok())),
+        Value::String(status_code) => Ok(status_code.trim().parse().ok()),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_fields_round_trip() {
+        let body =
+            r#"{"timestamp":"t1","level":"info","message":"a","trace_id":"abc","user":{"id":3}}"#;
+        let log: LogEntry = serde_json::from_str(body).unwrap();
+        assert_eq!(log.field("trace_id").as_deref(), Some("abc"));
+        assert_eq!(log.field("user").as_deref(), Some(r#"{"id":3}"#));
+
+        let saved: Value = serde_json::to_value(&log).unwrap();
+        assert_eq!(saved["trace_id"], "abc");
+        assert_eq!(saved["user"]["id"], 3);
+    }
+
+    #[test]
+    fn test_status_code_from_text_or_number() {
+        let log: LogEntry = serde_json::from_str(
+            r#"{"timestamp":"","level":"info","message":"","status_code":"503"}"#,
+        )
+        .unwrap();
+        assert_eq!(log.status_code, Some(503));
+        assert_eq!(serde_json::to_value(&log).unwrap()["status_code"], 503);
+
+        let log: LogEntry = serde_json::from_str(
+            r#"{"timestamp":"","level":"info","message":"","status_code":""}"#,
+        )
+        .unwrap();
+        assert_eq!(log.status_code, None);
+    }
+
+    #[test]
+    fn test_saved_logs_are_processed_again() {
+        let mut log = LogEntry::from_message(String::new());
+        log.is_process = true;
+        let mut saved = serde_json::to_value(&log).unwrap();
+        assert!(saved.get("is_process").is_none());
+
+        // as saved by older versions
+        saved["is_process"] = Value::Bool(true);
+        let log: LogEntry = serde_json::from_value(saved).unwrap();
+        assert!(!log.is_process);
+        assert!(!log.extra_fields.contains_key("is_process"));
+    }
+}
@@ -0,0 +1,134 @@
+use log::trace;
+
+use super::{
+    log_entry::{LogEntry, LogLevel},
+    log_processor_options::LogProcessorOptions,
+    message_search::MessageSearch,
+    time_range::TimeRange,
+    where_expr::WhereExpr,
+};
+
+/// Filters applied locally to every entry for inputs that cannot filter server-side.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    level_filter: Option<Vec<LogLevel>>,
+    status_filter: Vec<(u16, u16)>,
+    slower_than: Option<f64>,
+    time_range: Option<TimeRange>,
+    field_filters: Vec<(String, String)>,
+    where_expr: Option<WhereExpr>,
+    message_search: Option<MessageSearch>,
+}
+
+impl LogFilter {
+    pub fn create(option: &LogProcessorOptions) -> Result<Self, String> {
+        Ok(LogFilter {
+            level_filter: option.level_filter.clone(),
+            status_filter: option.status_filter.clone(),
+            slower_than: option.slower_than,
+            time_range: option.time_range,
+            field_filters: option.field_filters.clone(),
+            where_expr: WhereExpr::from_options(option)?,
+            message_search: MessageSearch::create(option)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level_filter.is_none()
+            && self.status_filter.is_empty()
+            && self.slower_than.is_none()
+            && self.time_range.is_none()
+            && self.field_filters.is_empty()
+            && self.where_expr.is_none()
+            && self.message_search.is_none()
+    }
+
+    pub fn trace_filters(&self) {
+        if let Some(levels) = &self.level_filter {
+            trace!("filter_logs : level {:?}", levels);
+        }
+        if !self.status_filter.is_empty() {
+            trace!("filter_logs : status {:?}", self.status_filter);
+        }
+        if let Some(slower_than) = self.slower_than {
+            trace!("filter_logs : slower than {} ms", slower_than);
+        }
+        if let Some(time_range) = &self.time_range {
+            trace!("filter_logs : time range {}", time_range);
+        }
+        for (name, value) in &self.field_filters {
+            trace!("filter_logs : field {} = {}", name, value);
+        }
+        if let Some(where_expr) = &self.where_expr {
+            trace!("filter_logs : where {:?}", where_expr);
+        }
+        if let Some(message_search) = &self.message_search {
+            trace!("filter_logs : message {:?}", message_search);
+        }
+    }
+
+    pub fn matches(&self, log: &LogEntry) -> bool {
+        self.matches_level(log)
+            && self.matches_status(log)
+            && self.matches_slower_than(log)
+            && self.matches_message(log)
+            && self.matches_date(log)
+            && self.matches_fields(log)
+            && self.matches_where(log)
+    }
+
+    fn matches_level(&self, log: &LogEntry) -> bool {
+        match &self.level_filter {
+            Some(levels) => levels.contains(&log.level),
+            None => true,
+        }
+    }
+
+    fn matches_status(&self, log: &LogEntry) -> bool {
+        if self.status_filter.is_empty() {
+            return true;
+        }
+        match log.status_code {
+            Some(status_code) => self
+                .status_filter
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&status_code)),
+            None => false,
+        }
+    }
+
+    fn matches_slower_than(&self, log: &LogEntry) -> bool {
+        match self.slower_than {
+            Some(slower_than) => log.process_time > slower_than,
+            None => true,
+        }
+    }
+
+    fn matches_message(&self, log: &LogEntry) -> bool {
+        match &self.message_search {
+            Some(message_search) => message_search.matches(log),
+            None => true,
+        }
+    }
+
+    fn matches_date(&self, log: &LogEntry) -> bool {
+        match (self.time_range, log.time_unix) {
+            (None, _) => true,
+            (Some(time_range), Some(time_unix)) => time_range.contains(time_unix),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn matches_where(&self, log: &LogEntry) -> bool {
+        match &self.where_expr {
+            Some(where_expr) => where_expr.matches(log),
+            None => true,
+        }
+    }
+
+    fn matches_fields(&self, log: &LogEntry) -> bool {
+        self.field_filters
+            .iter()
+            .all(|(name, value)| log.field(name).as_deref() == Some(value.as_str()))
+    }
+}
@@ -0,0 +1,181 @@
+use log::{debug, trace};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{
+    grok::{is_grok, GrokLibrary},
+    log_entry::LogEntry,
+};
+
+/// Name of the built-in pattern for `ip - METHOD /path status - N ms` access lines.
+pub const ACCESS_PARSER: &str = "access";
+const ACCESS_PATTERN: &str = r"^(?P<ip_address>[:\d\w.]+)\s-\s(?P<http_method>\w+)\s(?P<url>[\/\w]+)\s(?P<status_code>\d+)\s-\s(?P<process_time>[\d.]+).ms$";
+
+/// A named regex, or grok expression, whose named capture groups are copied onto the
+/// `LogEntry` fields of the same name. Groups without a matching field go to `extra_fields`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ParserDefinition {
+    pub name: String,
+    pub pattern: String,
+}
+
+impl ParserDefinition {
+    /// Parses the `NAME=PATTERN` form given on the command line.
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        match arg.split_once('=') {
+            Some((name, pattern)) if !name.is_empty() && !pattern.is_empty() => {
+                Ok(ParserDefinition {
+                    name: name.to_string(),
+                    pattern: pattern.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Failed to parse parser `{}`, expected NAME=PATTERN",
+                arg
+            )),
+        }
+    }
+
+    /// Compiles a grok expression into its regex form, regex patterns are left unchanged.
+    pub fn compile_grok(self, library: &GrokLibrary) -> Result<Self, String> {
+        if !is_grok(&self.pattern) {
+            return Ok(self);
+        }
+        let pattern = library
+            .compile(&self.pattern)
+            .map_err(|err| format!("Failed to compile parser {}: {}", self.name, err))?;
+        Ok(ParserDefinition {
+            name: self.name,
+            pattern,
+        })
+    }
+}
+
+/// Parser config file, `{"parsers": [{"name": ..., "pattern": ...}], "grok_patterns": {...}}`.
+#[derive(Debug, Deserialize)]
+pub struct ParserConfig {
+    pub parsers: Vec<ParserDefinition>,
+
+    #[serde(default)]
+    pub grok_patterns: HashMap<String, String>,
+}
+
+impl ParserConfig {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let body = fs::read_to_string(path)
+            .map_err(|err| format!("Failed reading parser config: {}", err))?;
+        serde_json::from_str(&body).map_err(|err| format!("Failed parsing parser config: {}", err))
+    }
+}
+
+struct LineParser {
+    name: String,
+    regex: Regex,
+}
+
+/// Ordered list of parsers tried against `LogEntry::message`, the first match wins. The
+/// built-in access pattern is always tried last.
+pub struct ParserRegistry {
+    parsers: Vec<LineParser>,
+}
+
+impl ParserRegistry {
+    pub fn create(definitions: &[ParserDefinition]) -> Result<Self, String> {
+        let builtin = ParserDefinition {
+            name: ACCESS_PARSER.to_string(),
+            pattern: ACCESS_PATTERN.to_string(),
+        };
+        let parsers = definitions
+            .iter()
+            .chain(std::iter::once(&builtin))
+            .map(|definition| {
+                let regex = Regex::new(&definition.pattern).map_err(|err| {
+                    format!("Failed to compile parser {}: {}", definition.name, err)
+                })?;
+                Ok(LineParser {
+                    name: definition.name.clone(),
+                    regex,
+                })
+            })
+            .collect::<Result<Vec<LineParser>, String>>()?;
+        debug!(
+            "parsers: {:?}",
+            parsers
+                .iter()
+                .map(|parser| &parser.name)
+                .collect::<Vec<_>>()
+        );
+        Ok(ParserRegistry { parsers })
+    }
+
+    /// Fills `log` from the first parser matching its message. Returns the parser name.
+    pub fn parse(&self, log: &mut LogEntry) -> Option<&str> {
+        let (parser, captures) = self.parsers.iter().find_map(|parser| {
+            parser
+                .regex
+                .captures(&log.message)
+                .map(|captures| (parser, captures))
+        })?;
+
+        let fields: Vec<(&str, String)> = parser
+            .regex
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                captures
+                    .name(name)
+                    .map(|value| (name, value.as_str().to_string()))
+            })
+            .collect();
+        for (name, value) in fields {
+            if !log.set_field(name, &value) {
+                trace!("parser {}: extra field {}", parser.name, name);
+                log.extra_fields
+                    .insert(name.to_string(), Value::String(value));
+            }
+        }
+        Some(&parser.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> LogEntry {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": "",
+            "level": "info",
+            "message": message,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_builtin_access_parser() {
+        let parsers = ParserRegistry::create(&[]).unwrap();
+        let mut log = entry("10.0.0.1 - GET /api/users 200 - 12.5 ms");
+        assert_eq!(parsers.parse(&mut log), Some(ACCESS_PARSER));
+        assert_eq!(log.ip_address, "10.0.0.1");
+        assert_eq!(log.url, "/api/users");
+        assert_eq!(log.status_code, Some(200));
+        assert_eq!(log.process_time, 12.5);
+    }
+
+    #[test]
+    fn test_parsers_are_tried_in_order() {
+        let parsers = ParserRegistry::create(&[
+            ParserDefinition::from_arg(r"worker=job (?P<url>\S+) failed: (?P<error>.+)").unwrap(),
+            ParserDefinition::from_arg(r"any=(?P<url>.+)").unwrap(),
+        ])
+        .unwrap();
+        let mut log = entry("job /sync/users failed: timeout");
+        assert_eq!(parsers.parse(&mut log), Some("worker"));
+        assert_eq!(log.url, "/sync/users");
+        assert_eq!(log.error, "timeout");
+
+        assert!(ParserDefinition::from_arg("missing-pattern").is_err());
+    }
+}
@@ -1,6 +1,21 @@
-use crate::processor::{get_log::{es_get::ESGetStrategy, file_get::FileGetStrategy, url_get::UrlGetStrategy}, log_processor_options::{LogInput, LogOutput, LogProcessorOptions, LogSave}, output_log::{count_output::CountOutputStrategy, json_output::JsonOutputStrategy, json_pretty_output::JsonPrettyOutputStrategy, summary_output::SummaryOutputStrategy}, save_log::{es_save::ESSaveStrategy, file_save::FileSaveStrategy}};
+use crate::processor::{
+    get_log::{es_get::ESGetStrategy, file_get::FileGetStrategy, url_get::UrlGetStrategy},
+    log_processor_options::{LogInput, LogOutput, LogProcessorOptions, LogSave},
+    output_log::{
+        count_output::CountOutputStrategy, csv_output::CsvOutputStrategy,
+        histogram_output::HistogramOutputStrategy, json_output::JsonOutputStrategy,
+        json_pretty_output::JsonPrettyOutputStrategy, logfmt_output::LogfmtOutputStrategy,
+        summary_output::SummaryOutputStrategy, top_output::TopOutputStrategy,
+    },
+    save_log::{es_save::ESSaveStrategy, file_save::FileSaveStrategy},
+};
 
-use super::{command::Cli, db::EsClient, log_trait::{GetLogTrait, OutputLogTrait, SaveLogTrait}};
+use super::{
+    command::Cli,
+    db::EsClient,
+    log_trait::{GetLogTrait, OutputLogTrait, SaveLogTrait},
+    message_search::MessageSearch,
+};
 use log::{debug, info};
 use std::sync::OnceLock;
 
@@ -8,20 +23,44 @@ pub struct LogProcessor {
     get_impl: Box<dyn GetLogTrait>,
     save_impl: Option<Box<dyn SaveLogTrait>>,
     output_impl: Option<Box<dyn OutputLogTrait>>,
+    highlight: Option<MessageSearch>,
 }
 
 impl LogProcessor {
-    pub fn run(&self) -> Result<(), String> {
+    /// Pulls entries from the input one at a time and hands each to the save and output
+    /// strategies, so only the entries buffered by a strategy are ever held in memory.
+    pub fn run(&mut self) -> Result<(), String> {
         let logs = (*self.get_impl)
             .get()
             .map_err(|err| format!("Failed Get Logs: {}", err))?;
 
-        if let Some(save_impl) = &self.save_impl {
-            (*save_impl).save(logs.as_ref())?;
+        for log in logs {
+            let log = log.map_err(|err| format!("Failed Get Logs: {}", err))?;
+
+            if let Some(save_impl) = &mut self.save_impl {
+                save_impl.save(&log)?;
+            }
+
+            if let Some(output_impl) = &mut self.output_impl {
+                match &self.highlight {
+                    Some(highlight) => {
+                        let mut log = log.clone();
+                        log.message = highlight.highlight(&log.message);
+                        output_impl.output(&log)?;
+                    }
+                    None => output_impl.output(&log)?,
+                }
+            }
+        }
+
+        self.get_impl.finish()?;
+
+        if let Some(save_impl) = &mut self.save_impl {
+            save_impl.finish()?;
         }
 
-        if let Some(output_impl) = &self.output_impl {
-            (*output_impl).output(logs.as_ref())?;
+        if let Some(output_impl) = &mut self.output_impl {
+            output_impl.finish()?;
         }
 
         Ok(())
@@ -32,7 +71,7 @@ pub struct LogProcessorFactory {}
 
 impl LogProcessorFactory {
     pub fn from_cli(cli: &Cli) -> Result<LogProcessor, String> {
-        let option = LogProcessorOptions::from_cli(&cli).unwrap();
+        let option = LogProcessorOptions::from_cli(cli)?;
         let mut level = log::LevelFilter::Off;
         if option.verbose {
             level = log::LevelFilter::Trace;
@@ -44,9 +83,6 @@ impl LogProcessorFactory {
 
         info!("option created");
         debug!("{:?}", option);
-        static ES_CLIENT: OnceLock<EsClient> = OnceLock::new();
-
-        let es_client = ES_CLIENT.get_or_init(|| EsClient::create().unwrap());
 
         let get_impl: Result<Box<dyn GetLogTrait>, String> = match &option.input {
             LogInput::File(x) => {
@@ -58,7 +94,7 @@ impl LogProcessorFactory {
                 Ok(Box::new(url_get))
             }
             LogInput::EsIndex(x) => {
-                let es_get = ESGetStrategy::create(x.clone(), option.clone(), &es_client)?;
+                let es_get = ESGetStrategy::create(x.clone(), option.clone(), es_client()?)?;
                 Ok(Box::new(es_get))
             } // cool that i don't need this
               // _ => Err("Input command not implemented".to_string()),
@@ -72,7 +108,7 @@ impl LogProcessorFactory {
             }
             LogSave::EsIndex(x) => {
                 let es_save =
-                    ESSaveStrategy::create_from_options(x.clone(), option.clone(), &es_client)?;
+                    ESSaveStrategy::create_from_options(x.clone(), option.clone(), es_client()?)?;
                 Ok(Some(Box::new(es_save)))
             }
             LogSave::None => Ok(None),
@@ -80,17 +116,53 @@ impl LogProcessorFactory {
         let save_impl = save_impl?;
 
         let out_impl: Result<Option<Box<dyn OutputLogTrait>>, String> = match &option.output {
-            LogOutput::Json => Ok(Some(Box::new(JsonOutputStrategy {}))),
+            LogOutput::Json => Ok(Some(Box::new(JsonOutputStrategy::create_from_options(
+                &option,
+            )))),
             LogOutput::PrettyJson => Ok(Some(Box::new(JsonPrettyOutputStrategy {}))),
-            LogOutput::Count => Ok(Some(Box::new(CountOutputStrategy {}))),
-            LogOutput::Summary => Ok(Some(Box::new(SummaryOutputStrategy {}))),
+            LogOutput::Count => Ok(Some(Box::new(CountOutputStrategy::default()))),
+            LogOutput::Summary => Ok(Some(Box::new(SummaryOutputStrategy::create_from_options(
+                &option,
+            )))),
+            LogOutput::Logfmt => Ok(Some(Box::new(LogfmtOutputStrategy {}))),
+            LogOutput::Csv => Ok(Some(Box::new(CsvOutputStrategy::create_from_options(
+                &option, b',',
+            )?))),
+            LogOutput::Tsv => Ok(Some(Box::new(CsvOutputStrategy::create_from_options(
+                &option, b'\t',
+            )?))),
+            LogOutput::Histogram => Ok(Some(Box::new(
+                HistogramOutputStrategy::create_from_options(&option),
+            ))),
+            LogOutput::Top => Ok(Some(Box::new(TopOutputStrategy::create_from_options(
+                &option,
+            )))),
             LogOutput::None => Ok(None),
         };
         let out_impl = out_impl?;
+
+        let highlight = if option.highlight && !option.invert_match {
+            MessageSearch::create(&option)?
+        } else {
+            None
+        };
         Ok(LogProcessor {
-            get_impl: get_impl,
+            get_impl,
             output_impl: out_impl,
-            save_impl: save_impl,
+            save_impl,
+            highlight,
         })
     }
 }
+
+/// Connects to Elasticsearch on first use, so inputs and outputs that never touch an index
+/// do not need the `ELASTIC_*` environment variables.
+fn es_client() -> Result<&'static EsClient, String> {
+    static ES_CLIENT: OnceLock<EsClient> = OnceLock::new();
+
+    if let Some(es_client) = ES_CLIENT.get() {
+        return Ok(es_client);
+    }
+    let es_client = EsClient::create()?;
+    Ok(ES_CLIENT.get_or_init(|| es_client))
+}
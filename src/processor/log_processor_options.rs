@@ -1,18 +1,45 @@
-use chrono::{Duration, Local, Timelike};
+use chrono::Utc;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf, time};
 
 use super::{
     command::{Cli, InputCommand},
+    decode_log::multiline::DEFAULT_CONTINUATION_PATTERN,
+    duration::parse_duration_ms,
+    grok::GrokLibrary,
     log_entry::LogLevel,
+    log_parser::{ParserConfig, ParserDefinition},
+    time_range::TimeRange,
+    timestamp::DefaultTimezone,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum LogInput {
-    File(PathBuf),
+    File(Vec<PathBuf>),
     Url(String),
     EsIndex(String),
 }
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Auto,
+    Json,
+    Text,
+    Clf,
+    Logfmt,
+    Syslog,
+    Journal,
+    Csv,
+    Tsv,
+}
+/// Rules for assembling entries that span several lines, like stack traces.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MultilineOptions {
+    pub start: Option<String>,
+    pub continuation: Option<String>,
+    pub max_lines: usize,
+    pub timeout: time::Duration,
+}
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum LogSave {
     File(PathBuf),
@@ -25,20 +52,65 @@ pub enum LogOutput {
     PrettyJson,
     Count,
     Summary,
+    Logfmt,
+    Csv,
+    Tsv,
+    Histogram,
+    Top,
     None,
 }
 
+/// Number of values printed by `--top` without `--top-count`.
+const DEFAULT_TOP_COUNT: usize = 10;
+
+/// Shortest `--interval`, shorter buckets are mostly empty rows.
+const MIN_HISTOGRAM_INTERVAL_MS: i64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramFormat {
+    Chart,
+    Json,
+    Csv,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LogProcessorOptions {
     pub input: LogInput,
+    pub input_format: InputFormat,
+    pub column_map: Vec<(String, String)>,
+    pub multiline: Option<MultilineOptions>,
+    pub timestamp_formats: Vec<String>,
+    pub timezone: Option<String>,
     pub reverse: bool,
     pub limit: i64,
-    pub date_filter_string: Option<String>,
-    pub level_filter: Option<LogLevel>,
-    pub date_filter: Option<(i64, i64)>,
+    pub level_filter: Option<Vec<LogLevel>>,
+    pub status_filter: Vec<(u16, u16)>,
+    pub slower_than: Option<f64>,
+    pub time_range: Option<TimeRange>,
+    pub field_filters: Vec<(String, String)>,
+    pub where_filters: Vec<String>,
+    pub ip_filter: Vec<IpNet>,
+    pub exclude_ip: Vec<IpNet>,
+    pub grep: Option<String>,
+    pub search: Vec<String>,
+    pub ignore_case: bool,
+    pub invert_match: bool,
+    pub highlight: bool,
     pub truncate_on_save: bool,
     pub save: LogSave,
     pub output: LogOutput,
+    pub summary_fields: Vec<String>,
+    pub output_columns: Vec<String>,
+    pub histogram_interval: i64,
+    pub histogram_format: HistogramFormat,
+    pub top_field: Option<String>,
+    pub top_count: usize,
+    pub top_group_by: Option<String>,
+    pub with_source_file: bool,
+    pub follow: bool,
+    pub poll_interval: time::Duration,
+    pub parsers: Vec<ParserDefinition>,
+    pub route_templates: Vec<String>,
     pub verbose: bool, // es auth data
 }
 
@@ -87,18 +159,78 @@ impl LogProcessorOptions {
             } => LogInput::EsIndex(x.clone()),
             _ => return Err("Input command not provided".to_string()),
         };
+        let input_format = parse_input_format(&cli.input_format)?;
+        let column_map = cli
+            .column_map
+            .iter()
+            .map(|column| parse_column_map(column))
+            .collect::<Result<Vec<(String, String)>, String>>()?;
+        let multiline = parse_multiline(cli)?;
+        let timestamp_formats = cli.timestamp_format.clone();
+        let timezone = cli.timezone.clone();
         let reverse = cli.reverse;
-        let limit = cli.limit.unwrap_or(100_000 as i64);
-        let date_filter_string = cli.date_filter.clone();
-        let date_filter: Option<(i64, i64)> = date_filter_string
-            .as_ref()
-            .map(|ds| parse_date_filter(ds))
-            .transpose()?;
-
-        let level_filter = match &cli.level {
-            None => None,
-            Some(x) => parse_level_filter(x)?,
+        let follow = cli.follow;
+        if follow {
+            if let LogInput::EsIndex(_) = input {
+                return Err("--follow is only supported for file and url input".to_string());
+            }
+            if reverse {
+                return Err("--reverse can't be used with --follow".to_string());
+            }
+            if let (LogInput::Url(_), InputFormat::Csv | InputFormat::Tsv) = (&input, input_format)
+            {
+                return Err("--follow of url input doesn't support csv and tsv".to_string());
+            }
+            if let (LogInput::Url(_), Some(_)) = (&input, &multiline) {
+                return Err("--follow of url input doesn't support --multiline".to_string());
+            }
+        }
+        let poll_interval = time::Duration::try_from_secs_f64(cli.poll_interval)
+            .ok()
+            .filter(|interval| !interval.is_zero())
+            .ok_or_else(|| "--poll-interval must be a positive number of seconds".to_string())?;
+        // following never ends, so only stop after --limit logs when it is given
+        let limit = match (cli.limit, follow) {
+            (Some(limit), _) => limit,
+            (None, true) => i64::MAX,
+            (None, false) => 100_000_i64,
         };
+        let default_timezone = cli
+            .timezone
+            .as_deref()
+            .map(DefaultTimezone::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let time_range = parse_time_range(cli, &default_timezone)?;
+
+        let level_filter = parse_level_filter(cli.level.as_deref(), cli.min_level.as_deref())?;
+        let status_filter = cli
+            .status
+            .as_deref()
+            .map(parse_status_filter)
+            .transpose()?
+            .unwrap_or_default();
+        let slower_than = cli
+            .slower_than
+            .as_deref()
+            .map(parse_slower_than)
+            .transpose()?;
+        let field_filters = cli
+            .field
+            .iter()
+            .map(|field| parse_field_filter(field))
+            .collect::<Result<Vec<(String, String)>, String>>()?;
+        let where_filters = cli.r#where.clone();
+        let ip_filter = parse_ip_nets(&cli.ip)?;
+        let exclude_ip = parse_ip_nets(&cli.exclude_ip)?;
+        let grep = cli.grep.clone();
+        let search = cli.search.clone();
+        let ignore_case = cli.ignore_case;
+        let invert_match = cli.invert_match;
+        let highlight = cli.highlight;
+        if (invert_match || highlight) && grep.is_none() && search.is_empty() {
+            return Err("--invert-match and --highlight need --grep or --search".to_string());
+        }
         let truncate_on_save = cli.truncate;
 
         let mut output = LogOutput::None;
@@ -111,7 +243,28 @@ impl LogProcessorOptions {
             output = LogOutput::Count;
         } else if cli.summary {
             output = LogOutput::Summary;
+        } else if cli.logfmt {
+            output = LogOutput::Logfmt;
+        } else if cli.csv {
+            output = LogOutput::Csv;
+        } else if cli.tsv {
+            output = LogOutput::Tsv;
+        } else if cli.histogram {
+            output = LogOutput::Histogram;
+        } else if cli.top.is_some() {
+            output = LogOutput::Top;
+        }
+
+        let summary_fields = cli.summary_field.clone();
+        let output_columns = cli.columns.clone();
+        let histogram_interval = parse_histogram_interval(&cli.interval)?;
+        let histogram_format = parse_histogram_format(&cli.histogram_format)?;
+        let top_field = cli.top.clone();
+        if top_field.is_none() && (cli.top_count.is_some() || cli.group_by.is_some()) {
+            return Err("--top-count and --group-by need --top".to_string());
         }
+        let top_count = cli.top_count.unwrap_or(DEFAULT_TOP_COUNT);
+        let top_group_by = cli.group_by.clone();
 
         let mut save = LogSave::None;
         if cli.save_to_file.is_some() {
@@ -119,85 +272,305 @@ impl LogProcessorOptions {
         } else if cli.save_to_es_index.is_some() {
             save = LogSave::EsIndex(cli.save_to_es_index.clone().unwrap())
         }
+        // colors would end up in the json, csv and logfmt values and in the aggregated fields
+        if highlight && !matches!(output, LogOutput::PrettyJson) {
+            return Err("--highlight only works with --pretty-json".to_string());
+        }
+        if follow {
+            // these only write their result once the input ends, which following never does
+            if let LogOutput::Count | LogOutput::Summary | LogOutput::Histogram | LogOutput::Top =
+                output
+            {
+                return Err(
+                    "--follow can't be used with --count, --summary, --histogram and --top"
+                        .to_string(),
+                );
+            }
+            if let LogSave::File(_) | LogSave::EsIndex(_) = save {
+                return Err(
+                    "--follow can't be used with --save-to-file and --save-to-es-index".to_string(),
+                );
+            }
+        }
+        let mut grok_library = GrokLibrary::create();
+        for grok_patterns in &cli.grok_patterns {
+            grok_library.add_patterns_file(grok_patterns)?;
+        }
+        let mut parsers = cli
+            .parser
+            .iter()
+            .map(|parser| ParserDefinition::from_arg(parser))
+            .collect::<Result<Vec<ParserDefinition>, String>>()?;
+        if let Some(parser_config) = &cli.parser_config {
+            let parser_config = ParserConfig::from_file(parser_config)?;
+            for (name, pattern) in &parser_config.grok_patterns {
+                grok_library.add_pattern(name, pattern);
+            }
+            parsers.extend(parser_config.parsers);
+        }
+        let parsers = parsers
+            .into_iter()
+            .map(|parser| parser.compile_grok(&grok_library))
+            .collect::<Result<Vec<ParserDefinition>, String>>()?;
+
+        let route_templates = cli.route.clone();
+
+        let with_source_file = cli.source_file;
         let verbose = cli.verbose;
 
         Ok(LogProcessorOptions {
-            input: input,
+            input,
+            input_format,
+            column_map,
+            multiline,
+            timestamp_formats,
+            timezone,
             reverse,
             limit,
-            date_filter,
-            date_filter_string,
+            time_range,
+            field_filters,
+            where_filters,
+            ip_filter,
+            exclude_ip,
+            grep,
+            search,
+            ignore_case,
+            invert_match,
+            highlight,
             output,
+            summary_fields,
+            output_columns,
+            histogram_interval,
+            histogram_format,
+            top_field,
+            top_count,
+            top_group_by,
             truncate_on_save,
             save,
+            with_source_file,
+            follow,
+            poll_interval,
+            parsers,
+            route_templates,
             verbose,
             level_filter,
+            status_filter,
+            slower_than,
         })
     }
 }
 
-fn parse_level_filter(level: &str) -> Result<Option<LogLevel>, String> {
-    match level.to_lowercase().as_str() {
-        "debug" | "deb" | "d" => Ok(Some(LogLevel::DEBUG)),
-        "error" | "err" | "e" | "ror" => Ok(Some(LogLevel::ERROR)),
-        "info" | "in" | "i" | "inf" => Ok(Some(LogLevel::INFO)),
-        "none" | "non" | "n" | "no" => Ok(Some(LogLevel::NONE)),
-        "warn" | "war" | "w" => Ok(Some(LogLevel::WARN)),
-        _ => Ok(None),
+/// Builds the set of levels to keep from `--level`, a comma separated list, and
+/// `--min-level`. With both, only the levels in the list at or above the minimum are kept.
+fn parse_level_filter(
+    levels: Option<&str>,
+    min_level: Option<&str>,
+) -> Result<Option<Vec<LogLevel>>, String> {
+    let mut level_filter: Option<Vec<LogLevel>> = levels
+        .map(|levels| levels.split(',').map(parse_level).collect())
+        .transpose()?;
+    if let Some(min_level) = min_level {
+        let min_severity = parse_level(min_level)?.severity().ok_or_else(|| {
+            "--min-level needs a level with a severity: debug, info, warn or error".to_string()
+        })?;
+        let levels = level_filter.unwrap_or_else(|| LogLevel::SEVERITY_ORDER.to_vec());
+        level_filter = Some(
+            levels
+                .into_iter()
+                .filter(|level| {
+                    level
+                        .severity()
+                        .is_some_and(|severity| severity >= min_severity)
+                })
+                .collect(),
+        );
     }
+    Ok(level_filter)
 }
 
-fn parse_date_filter(date_filter_string: &str) -> Result<(i64, i64), String> {
-    let today = Local::now().naive_local();
-    let yesterday = today - Duration::try_days(1).unwrap();
-
-    match date_filter_string {
-        "yesterday" => Ok((yesterday.and_utc().timestamp(), today.and_utc().timestamp())),
-        "today" | "0" => Ok((today.and_utc().timestamp(), today.and_utc().timestamp())),
-        nn_nn if nn_nn.contains('_') => {
-            let parts: Vec<&str> = nn_nn.split('_').collect();
-            if parts.len() == 2 {
-                if let (Ok(start), Ok(end)) = (parts[0].parse::<i64>(), parts[1].parse::<i64>()) {
-                    return Ok((start * 3600, end * 3600)); // Convert hours to seconds
-                } else {
-                    panic!("Failed to parse date_filter")
-                }
-            }
-            Err("Failed to parse date_filter nn_nn format".to_string())
+/// Parses `--status`, a comma separated list of codes (`404`), classes (`5xx`) and ranges
+/// (`400-499`), into inclusive ranges.
+fn parse_status_filter(statuses: &str) -> Result<Vec<(u16, u16)>, String> {
+    statuses
+        .split(',')
+        .map(|status| {
+            parse_status_range(status.trim()).ok_or_else(|| {
+                format!(
+                    "Failed to parse status `{}`, expected a code like 404, a class like 5xx or a range like 400-499",
+                    status.trim()
+                )
+            })
+        })
+        .collect()
+}
+
+fn parse_status_range(status: &str) -> Option<(u16, u16)> {
+    let parse_code = |code: &str| {
+        code.trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|code| (100..=599).contains(code))
+    };
+    if let Some(class) = status.strip_suffix("xx").or(status.strip_suffix("XX")) {
+        let start = parse_code(&format!("{}00", class))?;
+        return Some((start, start + 99));
+    }
+    match status.split_once('-') {
+        Some((start, end)) => parse_code(start)
+            .zip(parse_code(end))
+            .filter(|(start, end)| start <= end),
+        None => parse_code(status).map(|code| (code, code)),
+    }
+}
+
+/// Parses `--slower-than` into milliseconds, a plain number already is milliseconds.
+fn parse_slower_than(duration: &str) -> Result<f64, String> {
+    duration
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .or_else(|| parse_duration_ms(duration))
+        .filter(|duration| *duration >= 0.0)
+        .ok_or_else(|| {
+            format!(
+                "Failed to parse duration `{}`, expected e.g. 500ms or 1.5s",
+                duration
+            )
+        })
+}
+
+fn parse_level(level: &str) -> Result<LogLevel, String> {
+    match level.trim().to_lowercase().as_str() {
+        "debug" | "deb" | "d" => Ok(LogLevel::DEBUG),
+        "error" | "err" | "e" | "ror" => Ok(LogLevel::ERROR),
+        "info" | "in" | "i" | "inf" => Ok(LogLevel::INFO),
+        "none" | "non" | "n" | "no" => Ok(LogLevel::NONE),
+        "warn" | "war" | "w" => Ok(LogLevel::WARN),
+        other => LogLevel::from_name(other).ok_or_else(|| {
+            format!(
+                "Unknown level `{}`, expected debug, info, warn, error or none",
+                level
+            )
+        }),
+    }
+}
+
+fn parse_input_format(input_format: &str) -> Result<InputFormat, String> {
+    match input_format.to_lowercase().as_str() {
+        "auto" => Ok(InputFormat::Auto),
+        "json" | "ndjson" => Ok(InputFormat::Json),
+        "text" | "plain" => Ok(InputFormat::Text),
+        "clf" | "common" | "combined" | "nginx" | "apache" => Ok(InputFormat::Clf),
+        "logfmt" => Ok(InputFormat::Logfmt),
+        "syslog" | "rfc3164" | "rfc5424" => Ok(InputFormat::Syslog),
+        "journal" | "journald" => Ok(InputFormat::Journal),
+        "csv" => Ok(InputFormat::Csv),
+        "tsv" => Ok(InputFormat::Tsv),
+        _ => Err(format!("Unknown input format `{}`", input_format)),
+    }
+}
+
+/// Parses `--interval` into whole milliseconds, at least `MIN_HISTOGRAM_INTERVAL_MS`.
+fn parse_histogram_interval(interval: &str) -> Result<i64, String> {
+    let interval_ms = parse_duration_ms(interval)
+        .map(|interval| interval.round() as i64)
+        .ok_or_else(|| {
+            format!(
+                "Failed to parse interval `{}`, expected e.g. 1m, 5m, 1h or 1d",
+                interval
+            )
+        })?;
+    if interval_ms < MIN_HISTOGRAM_INTERVAL_MS {
+        return Err(format!(
+            "Failed to parse interval `{}`, it must be at least 1s",
+            interval
+        ));
+    }
+    Ok(interval_ms)
+}
+
+fn parse_histogram_format(histogram_format: &str) -> Result<HistogramFormat, String> {
+    match histogram_format.to_lowercase().as_str() {
+        "chart" | "bar" | "text" => Ok(HistogramFormat::Chart),
+        "json" => Ok(HistogramFormat::Json),
+        "csv" => Ok(HistogramFormat::Csv),
+        _ => Err(format!(
+            "Unknown histogram format `{}`, expected chart, json or csv",
+            histogram_format
+        )),
+    }
+}
+
+fn parse_field_filter(field: &str) -> Result<(String, String), String> {
+    match field.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!(
+            "Failed to parse field filter `{}`, expected NAME=VALUE",
+            field
+        )),
+    }
+}
+
+/// Parses addresses and CIDR ranges, an address is the network of just that address.
+fn parse_ip_nets(nets: &[String]) -> Result<Vec<IpNet>, String> {
+    nets.iter()
+        .map(|net| net.trim())
+        .filter(|net| !net.is_empty())
+        .map(|net| {
+            net.parse::<IpNet>()
+                .or_else(|_| net.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| {
+                    format!(
+                        "Failed to parse ip `{}`, expected an address or CIDR range",
+                        net
+                    )
+                })
+        })
+        .collect()
+}
+
+fn parse_multiline(cli: &Cli) -> Result<Option<MultilineOptions>, String> {
+    if !cli.multiline && cli.multiline_start.is_none() && cli.multiline_continuation.is_none() {
+        return Ok(None);
+    }
+    let continuation = match (&cli.multiline_start, &cli.multiline_continuation) {
+        (None, None) => Some(DEFAULT_CONTINUATION_PATTERN.to_string()),
+        (_, continuation) => continuation.clone(),
+    };
+    let timeout = time::Duration::try_from_secs_f64(cli.multiline_timeout)
+        .map_err(|_| "--multiline-timeout must be a positive number of seconds".to_string())?;
+    Ok(Some(MultilineOptions {
+        start: cli.multiline_start.clone(),
+        continuation,
+        max_lines: cli.multiline_max_lines,
+        timeout,
+    }))
+}
+
+fn parse_column_map(column: &str) -> Result<(String, String), String> {
+    match column.split_once('=') {
+        Some((column, field)) if !column.is_empty() && !field.is_empty() => {
+            Ok((column.to_string(), field.to_string()))
         }
-        n_dash if n_dash.ends_with('-') => {
-            let days_to_subtract = n_dash.trim_end_matches('-').parse::<i64>().unwrap_or(1);
-            let start = if days_to_subtract > 0 {
-                today
-                    - Duration::try_days(days_to_subtract)
-                        .expect("failed to parse date_filter. make sure n- is a number")
-            } else {
-                today
-                    - Duration::try_days(-days_to_subtract)
-                        .expect("failed to parse date_filter. make sure n- is a number")
-            };
-            let end = today;
-            Ok((
-                start
-                    .with_hour(0)
-                    .unwrap()
-                    .with_minute(0)
-                    .unwrap()
-                    .with_second(0)
-                    .unwrap()
-                    .and_utc()
-                    .timestamp(),
-                end.with_hour(23)
-                    .unwrap()
-                    .with_minute(59)
-                    .unwrap()
-                    .with_second(59)
-                    .unwrap()
-                    .and_utc()
-                    .timestamp(),
-            ))
+        _ => Err(format!(
+            "Failed to parse column map `{}`, expected COLUMN=FIELD",
+            column
+        )),
+    }
+}
+
+/// Builds the time range of `--since`/`--until`, or of the older `--date-filter`.
+fn parse_time_range(cli: &Cli, timezone: &DefaultTimezone) -> Result<Option<TimeRange>, String> {
+    let now = Utc::now();
+    match (&cli.date_filter, &cli.since, &cli.until) {
+        (None, None, None) => Ok(None),
+        (Some(date_filter), None, None) => {
+            TimeRange::from_date_filter(date_filter, timezone, now).map(Some)
+        }
+        (None, since, until) => {
+            TimeRange::from_since_until(since.as_deref(), until.as_deref(), timezone, now).map(Some)
         }
-        _ => Err("Failed to parse date_filter : format not found".to_string()),
+        (Some(_), _, _) => Err("--date-filter can't be used with --since and --until".to_string()),
     }
 }
 
@@ -210,10 +583,19 @@ mod tests {
         // Test with valid Cli input
         let cli = Cli {
             input: InputCommand {
-                input_file: Some(PathBuf::new()),
+                input_file: Some(vec![PathBuf::new()]),
                 input_es_index: None,
                 input_url: None,
             },
+            input_format: "auto".to_string(),
+            column_map: vec![],
+            multiline: false,
+            multiline_start: None,
+            multiline_continuation: None,
+            multiline_max_lines: 500,
+            multiline_timeout: 1.0,
+            timestamp_format: vec![],
+            timezone: None,
             reverse: false,
             json: false,
             truncate: false,
@@ -223,9 +605,41 @@ mod tests {
             save_to_es_index: None,
             limit: None,
             date_filter: None,
+            since: None,
+            until: None,
             pretty_json: false,
+            logfmt: false,
+            csv: false,
+            tsv: false,
+            columns: vec![],
+            histogram: false,
+            top: None,
+            top_count: None,
+            group_by: None,
+            interval: "5m".to_string(),
+            histogram_format: "chart".to_string(),
             verbose: false,
+            source_file: false,
+            follow: false,
+            poll_interval: 1.0,
+            parser: vec![],
+            parser_config: None,
+            grok_patterns: vec![],
+            route: vec![],
             level: None,
+            min_level: None,
+            status: None,
+            slower_than: None,
+            field: vec![],
+            r#where: vec![],
+            ip: vec![],
+            exclude_ip: vec![],
+            grep: None,
+            search: vec![],
+            ignore_case: false,
+            invert_match: false,
+            highlight: false,
+            summary_field: vec![],
         };
         let result = LogProcessorOptions::from_cli(&cli);
         assert!(result.is_ok());
@@ -237,10 +651,19 @@ mod tests {
         // Test with missing input command
         let cli = Cli {
             input: InputCommand {
-                input_file: Some(PathBuf::new()),
+                input_file: Some(vec![PathBuf::new()]),
                 input_es_index: None,
                 input_url: None,
             },
+            input_format: "auto".to_string(),
+            column_map: vec![],
+            multiline: false,
+            multiline_start: None,
+            multiline_continuation: None,
+            multiline_max_lines: 500,
+            multiline_timeout: 1.0,
+            timestamp_format: vec![],
+            timezone: None,
             reverse: false,
             json: false,
             truncate: false,
@@ -250,9 +673,41 @@ mod tests {
             save_to_es_index: None,
             limit: None,
             date_filter: Some("what".to_string()),
+            since: None,
+            until: None,
             pretty_json: false,
+            logfmt: false,
+            csv: false,
+            tsv: false,
+            columns: vec![],
+            histogram: false,
+            top: None,
+            top_count: None,
+            group_by: None,
+            interval: "5m".to_string(),
+            histogram_format: "chart".to_string(),
             verbose: false,
+            source_file: false,
+            follow: false,
+            poll_interval: 1.0,
+            parser: vec![],
+            parser_config: None,
+            grok_patterns: vec![],
+            route: vec![],
             level: None,
+            min_level: None,
+            status: None,
+            slower_than: None,
+            field: vec![],
+            r#where: vec![],
+            ip: vec![],
+            exclude_ip: vec![],
+            grep: None,
+            search: vec![],
+            ignore_case: false,
+            invert_match: false,
+            highlight: false,
+            summary_field: vec![],
         };
         let result = LogProcessorOptions::from_cli(&cli);
         assert!(result.is_err());
@@ -263,5 +718,67 @@ mod tests {
         // Add assertions for expected error message
     }
 
+    #[test]
+    fn test_parse_level_filter() {
+        assert_eq!(
+            parse_level_filter(Some("warn,e"), None),
+            Ok(Some(vec![LogLevel::WARN, LogLevel::ERROR]))
+        );
+        assert_eq!(
+            parse_level_filter(None, Some("info")),
+            Ok(Some(vec![LogLevel::INFO, LogLevel::WARN, LogLevel::ERROR]))
+        );
+        assert_eq!(
+            parse_level_filter(Some("debug,error,none"), Some("warning")),
+            Ok(Some(vec![LogLevel::ERROR]))
+        );
+        assert_eq!(parse_level_filter(None, None), Ok(None));
+        assert!(parse_level_filter(Some("info,loud"), None).is_err());
+        assert!(parse_level_filter(None, Some("none")).is_err());
+    }
+
+    #[test]
+    fn test_parse_status_filter() {
+        assert_eq!(
+            parse_status_filter("5xx, 400-404,429"),
+            Ok(vec![(500, 599), (400, 404), (429, 429)])
+        );
+        assert!(parse_status_filter("6xx").is_err());
+        assert!(parse_status_filter("499-400").is_err());
+        assert!(parse_status_filter("ok").is_err());
+    }
+
+    #[test]
+    fn test_parse_slower_than() {
+        assert_eq!(parse_slower_than("500ms"), Ok(500.0));
+        assert_eq!(parse_slower_than("1.5s"), Ok(1500.0));
+        assert_eq!(parse_slower_than("250"), Ok(250.0));
+        assert!(parse_slower_than("fast").is_err());
+    }
+
+    #[test]
+    fn test_highlight_needs_pretty_json() {
+        use clap::Parser;
+        let from_args = |args: &[&str]| {
+            let args = ["salog", "--input-file", "-", "--grep", "users"]
+                .iter()
+                .chain(args);
+            LogProcessorOptions::from_cli(&Cli::parse_from(args))
+        };
+        assert!(from_args(&["--highlight", "--pretty-json"]).is_ok());
+        for output in ["--json", "--csv", "--tsv", "--logfmt", "--count"] {
+            assert!(from_args(&["--highlight", output]).is_err(), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_parse_histogram_interval() {
+        assert_eq!(parse_histogram_interval("5m"), Ok(300_000));
+        assert_eq!(parse_histogram_interval("1s"), Ok(1000));
+        assert!(parse_histogram_interval("1ms").is_err());
+        assert!(parse_histogram_interval("0s").is_err());
+        assert!(parse_histogram_interval("often").is_err());
+    }
+
     // Add more tests for other scenarios...
 }
@@ -0,0 +1,200 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use log::trace;
+
+use super::{
+    log_entry::LogEntry, log_filter::LogFilter, log_parser::ParserRegistry, log_trait::LogStream,
+    route::RouteNormalizer, timestamp::TimestampParser,
+};
+
+pub fn process_logs_date<'a>(
+    logs: LogStream<'a>,
+    timestamps: &'a TimestampParser,
+) -> LogStream<'a> {
+    trace!("process_logs_date");
+    Box::new(logs.map(move |log| {
+        log.map(|mut log| {
+            log.process_date(timestamps);
+            log
+        })
+    }))
+}
+
+pub fn process_logs<'a>(
+    logs: LogStream<'a>,
+    parsers: &'a ParserRegistry,
+    routes: &'a RouteNormalizer,
+    timestamps: &'a TimestampParser,
+) -> LogStream<'a> {
+    trace!("process_logs");
+    let mut i: usize = 0;
+    Box::new(logs.map(move |log| {
+        if i.is_multiple_of(1000) {
+            trace!("process_logs: {}", i);
+        }
+        i += 1;
+        log.map(|mut log| {
+            log.process(parsers, routes, timestamps);
+            log
+        })
+    }))
+}
+
+pub fn filter_logs<'a>(logs: LogStream<'a>, filter: &'a LogFilter) -> LogStream<'a> {
+    if filter.is_empty() {
+        trace!("skip filter");
+        return logs;
+    }
+    filter.trace_filters();
+    Box::new(logs.filter(move |log| match log {
+        Ok(log) => filter.matches(log),
+        Err(_) => true,
+    }))
+}
+
+/// Takes the first `limit` entries, or the last `limit` entries newest-first when `reverse`
+/// is set. Reversing only ever buffers `limit` entries.
+pub fn limit_logs(logs: LogStream<'_>, reverse: bool, limit: i64) -> LogStream<'_> {
+    let limit = limit.max(0) as usize;
+    trace!("limit_logs {}", limit);
+    if !reverse {
+        trace!("skip reverse_logs");
+        return Box::new(logs.take(limit));
+    }
+
+    trace!("reverse_logs");
+    let mut logs = logs;
+    let mut tail: Option<VecDeque<LogEntry>> = None;
+    Box::new(std::iter::from_fn(move || {
+        if tail.is_none() {
+            let mut buffer: VecDeque<LogEntry> = VecDeque::new();
+            for log in logs.by_ref() {
+                let log = match log {
+                    Ok(log) => log,
+                    Err(err) => return Some(Err(err)),
+                };
+                if limit == 0 {
+                    continue;
+                }
+                if buffer.len() == limit {
+                    buffer.pop_front();
+                }
+                buffer.push_back(log);
+            }
+            tail = Some(buffer);
+        }
+        tail.as_mut().and_then(|buffer| buffer.pop_back()).map(Ok)
+    }))
+}
+
+/// Merges streams that are each ordered by `time_unix` into one ordered stream, holding
+/// only the next entry of every stream. Entries without `time_unix` are emitted as soon as
+/// they reach the head of their stream.
+pub fn merge_logs(mut streams: Vec<LogStream<'_>>) -> LogStream<'_> {
+    if streams.len() == 1 {
+        return streams.remove(0);
+    }
+    trace!("merge_logs {}", streams.len());
+    Box::new(MergeLogs {
+        heads: streams.iter().map(|_| None).collect(),
+        streams,
+        queue: BinaryHeap::new(),
+        pending: None,
+        started: false,
+    })
+}
+
+struct MergeLogs<'a> {
+    streams: Vec<LogStream<'a>>,
+    heads: Vec<Option<LogEntry>>,
+    queue: BinaryHeap<Reverse<(Option<i64>, usize)>>,
+    pending: Option<usize>,
+    started: bool,
+}
+
+impl<'a> MergeLogs<'a> {
+    fn pull(&mut self, index: usize) -> Result<(), String> {
+        match self.streams[index].next() {
+            Some(Ok(log)) => {
+                self.queue.push(Reverse((log.time_unix, index)));
+                self.heads[index] = Some(log);
+                Ok(())
+            }
+            Some(Err(err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Iterator for MergeLogs<'a> {
+    type Item = Result<LogEntry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            for index in 0..self.streams.len() {
+                if let Err(err) = self.pull(index) {
+                    return Some(Err(err));
+                }
+            }
+        }
+        if let Some(index) = self.pending.take() {
+            if let Err(err) = self.pull(index) {
+                return Some(Err(err));
+            }
+        }
+
+        let Reverse((_, index)) = self.queue.pop()?;
+        self.pending = Some(index);
+        self.heads[index].take().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(messages: &[&str]) -> LogStream<'static> {
+        let logs: Vec<Result<LogEntry, String>> = messages
+            .iter()
+            .enumerate()
+            .map(|(time_unix, message)| {
+                serde_json::from_str(&format!(
+                    r#"{{"timestamp":"","level":"info","message":"{}","time_unix":{}}}"#,
+                    message, time_unix
+                ))
+                .map_err(|err| err.to_string())
+            })
+            .collect();
+        Box::new(logs.into_iter())
+    }
+
+    fn messages(logs: LogStream<'_>) -> Vec<String> {
+        logs.map(|log| log.unwrap().message).collect()
+    }
+
+    #[test]
+    fn test_limit_logs_takes_first() {
+        let logs = limit_logs(entries(&["a", "b", "c"]), false, 2);
+        assert_eq!(messages(logs), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_limit_logs_reverse_takes_last() {
+        let logs = limit_logs(entries(&["a", "b", "c", "d"]), true, 2);
+        assert_eq!(messages(logs), vec!["d", "c"]);
+    }
+
+    #[test]
+    fn test_merge_logs_orders_by_time_unix() {
+        let logs = merge_logs(vec![
+            entries(&["a0", "a1", "a2"]),
+            entries(&["b0"]),
+            entries(&["c0", "c1"]),
+        ]);
+        assert_eq!(messages(logs), vec!["a0", "b0", "c0", "a1", "c1", "a2"]);
+    }
+}
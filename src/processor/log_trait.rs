@@ -1,13 +1,31 @@
 use super::log_entry::LogEntry;
 
+/// Lazily evaluated sequence of log entries, pulled one at a time by `LogProcessor::run`.
+pub type LogStream<'a> = Box<dyn Iterator<Item = Result<LogEntry, String>> + 'a>;
+
 pub trait GetLogTrait {
-    fn get(&self) -> Result<Vec<LogEntry>, String>;
+    fn get(&self) -> Result<LogStream<'_>, String>;
+
+    /// Called once after the last entry was pulled, to report on the input.
+    fn finish(&self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 pub trait SaveLogTrait {
-    fn save(&self, logs: &Vec<LogEntry>) -> Result<(), String>;
+    fn save(&mut self, log: &LogEntry) -> Result<(), String>;
+
+    /// Called once after the last entry, to write anything still buffered.
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 pub trait OutputLogTrait {
-    fn output(&self, logs: &Vec<LogEntry>) -> Result<(), String>;
+    fn output(&mut self, log: &LogEntry) -> Result<(), String>;
+
+    /// Called once after the last entry, to print aggregated results or closing tokens.
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
 }
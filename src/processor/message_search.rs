@@ -0,0 +1,171 @@
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+
+use super::{
+    es_regexp::es_regexp, log_entry::LogEntry, log_processor_options::LogProcessorOptions,
+};
+
+const HIGHLIGHT_START: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// `--grep` and `--search` over `message`. A message matches when it matches the grep regex
+/// and contains every search term, or when it doesn't with `--invert-match`.
+#[derive(Debug, Clone)]
+pub struct MessageSearch {
+    grep: Option<String>,
+    terms: Vec<String>,
+    ignore_case: bool,
+    invert: bool,
+    grep_regex: Option<Regex>,
+    term_regexes: Vec<Regex>,
+    highlight_regex: Regex,
+}
+
+impl MessageSearch {
+    pub fn create(option: &LogProcessorOptions) -> Result<Option<Self>, String> {
+        let terms: Vec<String> = option
+            .search
+            .iter()
+            .flat_map(|search| search.split_whitespace())
+            .map(str::to_string)
+            .collect();
+        if option.grep.is_none() && terms.is_empty() {
+            return Ok(None);
+        }
+        let compile = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(option.ignore_case)
+                .build()
+                .map_err(|err| format!("Failed to compile --grep `{}`: {}", pattern, err))
+        };
+        let grep_regex = option.grep.as_deref().map(compile).transpose()?;
+        let term_regexes = terms
+            .iter()
+            .map(|term| compile(&regex::escape(term)))
+            .collect::<Result<Vec<Regex>, String>>()?;
+        let highlight_pattern = option
+            .grep
+            .iter()
+            .map(|grep| format!("(?:{})", grep))
+            .chain(terms.iter().map(|term| regex::escape(term)))
+            .collect::<Vec<String>>()
+            .join("|");
+        Ok(Some(MessageSearch {
+            grep: option.grep.clone(),
+            terms,
+            ignore_case: option.ignore_case,
+            invert: option.invert_match,
+            grep_regex,
+            term_regexes,
+            highlight_regex: compile(&highlight_pattern)?,
+        }))
+    }
+
+    pub fn matches(&self, log: &LogEntry) -> bool {
+        let found = self
+            .grep_regex
+            .as_ref()
+            .is_none_or(|grep| grep.is_match(&log.message))
+            && self
+                .term_regexes
+                .iter()
+                .all(|term| term.is_match(&log.message));
+        found != self.invert
+    }
+
+    /// Wraps the matches in `message` in terminal colors.
+    pub fn highlight(&self, message: &str) -> String {
+        self.highlight_regex
+            .replace_all(message, format!("{}$0{}", HIGHLIGHT_START, HIGHLIGHT_END))
+            .to_string()
+    }
+
+    /// Elasticsearch queries on `message`: `regexp` for `--grep` and `match` with all terms
+    /// required for `--search`. The regexp runs on `message.keyword`, which the default
+    /// mapping leaves empty for messages over 256 characters, so those are fetched as well
+    /// and `--grep` has to be checked again on the hits, see `needs_recheck`.
+    pub fn to_es_query(&self) -> Result<Value, String> {
+        let mut queries: Vec<Value> = Vec::new();
+        if let Some(grep) = &self.grep {
+            let (pattern, case_insensitive) = es_regexp(grep)?;
+            let regexp = json!({"regexp": {"message.keyword": {
+                "value": pattern,
+                "case_insensitive": case_insensitive || self.ignore_case,
+            }}});
+            if self.invert {
+                // long messages don't match the regexp, so they are kept already
+                queries.push(regexp);
+            } else {
+                let no_keyword = json!({"bool": {"must_not": [
+                    {"exists": {"field": "message.keyword"}}
+                ]}});
+                queries.push(json!({"bool": {
+                    "should": [regexp, no_keyword],
+                    "minimum_should_match": 1,
+                }}));
+            }
+        }
+        if !self.terms.is_empty() {
+            queries.push(json!({"match": {"message": {
+                "query": self.terms.join(" "),
+                "operator": "and",
+            }}}));
+        }
+        Ok(if self.invert {
+            json!({"bool": {"must_not": [{"bool": {"must": queries}}]}})
+        } else {
+            json!({"bool": {"must": queries}})
+        })
+    }
+
+    /// True when the hits of `to_es_query` may not all match and `matches` must be checked
+    /// on each of them.
+    pub fn needs_recheck(&self) -> bool {
+        self.grep.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{command::Cli, log_processor_options::LogProcessorOptions};
+    use clap::Parser;
+
+    fn search(args: &[&str]) -> MessageSearch {
+        let args = ["salog", "--input-file", "-"].iter().chain(args);
+        let option = LogProcessorOptions::from_cli(&Cli::parse_from(args)).unwrap();
+        MessageSearch::create(&option).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_grep_and_search() {
+        let log = LogEntry::from_message("Payment FAILED for order 42".to_string());
+        assert!(search(&["--grep", r"order \d+"]).matches(&log));
+        assert!(!search(&["--search", "payment failed"]).matches(&log));
+        assert!(search(&["--search", "payment failed", "-i"]).matches(&log));
+        assert!(!search(&["--grep", "order", "--invert-match"]).matches(&log));
+
+        let highlighted = search(&["--grep", r"\d+", "--search", "for"]).highlight(&log.message);
+        assert_eq!(
+            highlighted,
+            "Payment FAILED \x1b[1;31mfor\x1b[0m order \x1b[1;31m42\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_grep_es_query_fetches_long_messages() {
+        let grep = search(&["--grep", "timeout"]);
+        assert!(grep.needs_recheck());
+        assert_eq!(
+            grep.to_es_query(),
+            Ok(json!({"bool": {"must": [{"bool": {
+                "should": [
+                    {"regexp": {"message.keyword": {"value": ".*timeout.*", "case_insensitive": false}}},
+                    {"bool": {"must_not": [{"exists": {"field": "message.keyword"}}]}},
+                ],
+                "minimum_should_match": 1,
+            }}]}}))
+        );
+        assert!(!search(&["--search", "timeout"]).needs_recheck());
+    }
+}
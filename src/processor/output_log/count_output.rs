@@ -1,11 +1,19 @@
 use crate::processor::{log_entry::LogEntry, log_trait::OutputLogTrait};
 
-
-pub struct CountOutputStrategy;
+#[derive(Default)]
+pub struct CountOutputStrategy {
+    count: usize,
+}
 
 impl OutputLogTrait for CountOutputStrategy {
-    fn output(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
-        println!("{}", logs.len());
+    fn output(&mut self, _log: &LogEntry) -> Result<(), String> {
+        self.count += 1;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        println!("{}", self.count);
 
         Ok(())
     }
@@ -0,0 +1,84 @@
+use std::io;
+
+use crate::processor::{
+    log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::OutputLogTrait,
+};
+
+/// Columns printed when `--columns` is not given.
+pub const DEFAULT_COLUMNS: &[&str] = &[
+    "timestamp",
+    "level",
+    "message",
+    "http_method",
+    "url",
+    "route",
+    "status_code",
+    "process_time",
+    "ip_address",
+    "error",
+];
+
+/// Prints the entries as CSV or TSV with a header line, one column per selected field. The
+/// writer buffers on its own, so in follow mode each record is flushed as it is written.
+pub struct CsvOutputStrategy {
+    writer: csv::Writer<io::Stdout>,
+    columns: Vec<String>,
+    flush_each: bool,
+}
+
+impl CsvOutputStrategy {
+    pub fn create_from_options(
+        option: &LogProcessorOptions,
+        delimiter: u8,
+    ) -> Result<Self, String> {
+        let columns = if option.output_columns.is_empty() {
+            DEFAULT_COLUMNS
+                .iter()
+                .map(|column| column.to_string())
+                .collect()
+        } else {
+            option.output_columns.clone()
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(io::stdout());
+        writer
+            .write_record(&columns)
+            .map_err(|err| format!("failed to output : {}", err))?;
+        let flush_each = option.follow;
+        if flush_each {
+            writer
+                .flush()
+                .map_err(|err| format!("failed to output : {}", err))?;
+        }
+        Ok(CsvOutputStrategy {
+            writer,
+            columns,
+            flush_each,
+        })
+    }
+}
+
+impl OutputLogTrait for CsvOutputStrategy {
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        let record = self
+            .columns
+            .iter()
+            .map(|column| log.field(column).unwrap_or_default());
+        self.writer
+            .write_record(record)
+            .map_err(|err| format!("failed to output : {}", err))?;
+        if self.flush_each {
+            self.writer
+                .flush()
+                .map_err(|err| format!("failed to output : {}", err))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|err| format!("failed to output : {}", err))
+    }
+}
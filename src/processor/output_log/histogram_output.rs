@@ -0,0 +1,279 @@
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use crate::processor::{
+    log_entry::LogEntry,
+    log_processor_options::{HistogramFormat, LogProcessorOptions},
+    log_trait::OutputLogTrait,
+};
+
+/// Width in characters of the longest bar of the chart.
+const BAR_WIDTH: usize = 50;
+
+/// Most buckets printed, logs spread over a longer time need a larger interval.
+const MAX_BUCKETS: i64 = 100_000;
+
+const LEVEL_COLUMNS: &[&str] = &["debug", "info", "warn", "error", "none"];
+const STATUS_CLASS_COLUMNS: &[&str] = &["1xx", "2xx", "3xx", "4xx", "5xx"];
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct Bucket {
+    count: usize,
+    levels: BTreeMap<String, usize>,
+    status_classes: BTreeMap<String, usize>,
+}
+
+impl Bucket {
+    fn add(&mut self, log: &LogEntry) {
+        self.count += 1;
+        *self
+            .levels
+            .entry(log.level.as_str().to_lowercase())
+            .or_insert(0) += 1;
+        if let Some(status_code) = log.status_code {
+            let status_class = format!("{}xx", status_code / 100);
+            *self.status_classes.entry(status_class).or_insert(0) += 1;
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistogramRow {
+    start: String,
+    #[serde(flatten)]
+    bucket: Bucket,
+}
+
+/// Counts entries per `time_unix` bucket of a fixed interval, aligned to UTC, and prints
+/// every bucket from the first to the last once the stream ends, so quiet periods show up
+/// as empty buckets.
+pub struct HistogramOutputStrategy {
+    interval: i64,
+    format: HistogramFormat,
+    buckets: BTreeMap<i64, Bucket>,
+    undated: usize,
+}
+
+impl HistogramOutputStrategy {
+    pub fn create_from_options(option: &LogProcessorOptions) -> Self {
+        HistogramOutputStrategy {
+            interval: option.histogram_interval,
+            format: option.histogram_format,
+            buckets: BTreeMap::new(),
+            undated: 0,
+        }
+    }
+
+    fn rows(&mut self) -> Result<Vec<HistogramRow>, String> {
+        let (Some(first), Some(last)) = (
+            self.buckets.keys().next().copied(),
+            self.buckets.keys().next_back().copied(),
+        ) else {
+            return Ok(Vec::new());
+        };
+        let bucket_count = (last - first) / self.interval + 1;
+        if bucket_count > MAX_BUCKETS {
+            return Err(format!(
+                "Failed to build histogram: {} to {} is {} buckets of --interval, more than {}, use a larger interval",
+                format_time(first),
+                format_time(last),
+                bucket_count,
+                MAX_BUCKETS
+            ));
+        }
+        Ok((first..=last)
+            .step_by(self.interval as usize)
+            .map(|start| HistogramRow {
+                start: format_time(start),
+                bucket: self.buckets.remove(&start).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+impl OutputLogTrait for HistogramOutputStrategy {
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        match log.time_unix {
+            Some(time_unix) => {
+                let start = time_unix.div_euclid(self.interval) * self.interval;
+                self.buckets.entry(start).or_default().add(log);
+            }
+            None => self.undated += 1,
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let rows = self.rows()?;
+        let mut stdout = io::stdout().lock();
+        match self.format {
+            HistogramFormat::Chart => write_chart(&mut stdout, &rows),
+            HistogramFormat::Json => serde_json::to_writer_pretty(&mut stdout, &rows)
+                .map_err(|err| err.to_string())
+                .and_then(|_| writeln!(stdout).map_err(|err| err.to_string())),
+            HistogramFormat::Csv => write_csv(&mut stdout, &rows),
+        }
+        .map_err(|err| format!("failed to output : {}", err))?;
+        if self.undated > 0 {
+            eprintln!(
+                "warning: {} logs have no time and are not in the histogram",
+                self.undated
+            );
+        }
+        Ok(())
+    }
+}
+
+fn format_time(time_unix: i64) -> String {
+    DateTime::from_timestamp_millis(time_unix)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| time_unix.to_string())
+}
+
+/// One line per bucket, the bar scaled to the fullest bucket, followed by the non-zero
+/// counts per level and status class.
+fn write_chart(out: &mut impl Write, rows: &[HistogramRow]) -> Result<(), String> {
+    let max = rows.iter().map(|row| row.bucket.count).max().unwrap_or(0);
+    let count_width = max.to_string().len();
+    for row in rows {
+        let bar_len = match max {
+            0 => 0,
+            max => (row.bucket.count * BAR_WIDTH).div_ceil(max),
+        };
+        let details: Vec<String> = row
+            .bucket
+            .levels
+            .iter()
+            .chain(&row.bucket.status_classes)
+            .map(|(name, count)| format!("{}={}", name, count))
+            .collect();
+        writeln!(
+            out,
+            "{} {:>count_width$} |{:<BAR_WIDTH$}| {}",
+            row.start,
+            row.bucket.count,
+            "#".repeat(bar_len),
+            details.join(" "),
+        )
+        .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, rows: &[HistogramRow]) -> Result<(), String> {
+    let mut writer = csv::Writer::from_writer(out);
+    let header = ["start", "count"]
+        .iter()
+        .chain(LEVEL_COLUMNS)
+        .chain(STATUS_CLASS_COLUMNS);
+    writer.write_record(header).map_err(|err| err.to_string())?;
+    for row in rows {
+        let bucket = &row.bucket;
+        let counts = LEVEL_COLUMNS
+            .iter()
+            .map(|level| bucket.levels.get(*level))
+            .chain(
+                STATUS_CLASS_COLUMNS
+                    .iter()
+                    .map(|status_class| bucket.status_classes.get(*status_class)),
+            )
+            .map(|count| count.copied().unwrap_or(0).to_string());
+        let record: Vec<String> = [row.start.clone(), bucket.count.to_string()]
+            .into_iter()
+            .chain(counts)
+            .collect();
+        writer
+            .write_record(&record)
+            .map_err(|err| err.to_string())?;
+    }
+    writer.flush().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::log_entry::LogLevel;
+
+    fn entry(time_unix: i64, level: LogLevel, status_code: Option<u16>) -> LogEntry {
+        let mut log = LogEntry::from_message(String::new());
+        log.time_unix = Some(time_unix);
+        log.level = level;
+        log.status_code = status_code;
+        log
+    }
+
+    fn histogram(format: HistogramFormat) -> HistogramOutputStrategy {
+        let mut histogram = HistogramOutputStrategy {
+            interval: 60_000,
+            format,
+            buckets: BTreeMap::new(),
+            undated: 0,
+        };
+        for log in [
+            entry(1_700_000_010_000, LogLevel::INFO, Some(200)),
+            entry(1_700_000_030_000, LogLevel::ERROR, Some(503)),
+            entry(1_700_000_190_000, LogLevel::WARN, None),
+        ] {
+            histogram.output(&log).unwrap();
+        }
+        histogram
+    }
+
+    #[test]
+    fn test_buckets_with_gaps() {
+        let rows = histogram(HistogramFormat::Json).rows().unwrap();
+        let starts: Vec<&str> = rows.iter().map(|row| row.start.as_str()).collect();
+        assert_eq!(
+            starts,
+            [
+                "2023-11-14T22:13:00Z",
+                "2023-11-14T22:14:00Z",
+                "2023-11-14T22:15:00Z",
+                "2023-11-14T22:16:00Z",
+            ]
+        );
+        assert_eq!(rows[0].bucket.count, 2);
+        assert_eq!(rows[0].bucket.levels["error"], 1);
+        assert_eq!(rows[0].bucket.status_classes["5xx"], 1);
+        assert_eq!(rows[1].bucket, Bucket::default());
+        assert_eq!(rows[3].bucket.levels["warn"], 1);
+    }
+
+    #[test]
+    fn test_too_many_buckets() {
+        let mut histogram = histogram(HistogramFormat::Json);
+        let last = 1_700_000_000_000 + MAX_BUCKETS * 60_000;
+        histogram
+            .output(&entry(last, LogLevel::INFO, None))
+            .unwrap();
+        assert!(histogram.rows().is_err());
+    }
+
+    #[test]
+    fn test_chart_and_csv() {
+        let rows = histogram(HistogramFormat::Chart).rows().unwrap();
+        let mut chart = Vec::new();
+        write_chart(&mut chart, &rows).unwrap();
+        let chart = String::from_utf8(chart).unwrap();
+        let first = chart.lines().next().unwrap();
+        assert!(first.starts_with("2023-11-14T22:13:00Z 2 |#####"));
+        assert!(first.ends_with("| error=1 info=1 2xx=1 5xx=1"));
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("start,count,debug,info,warn,error,none,1xx,2xx,3xx,4xx,5xx")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("2023-11-14T22:13:00Z,2,0,1,0,1,0,0,1,0,0,1")
+        );
+    }
+}
@@ -1,10 +1,51 @@
-use crate::processor::{log_entry::LogEntry, log_trait::OutputLogTrait};
+use std::io::{self, Write};
 
-pub struct JsonOutputStrategy;
+use crate::processor::{
+    log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::OutputLogTrait,
+};
+
+/// Prints the entries as one JSON array, writing each element as it arrives. In follow
+/// mode the array would never be closed, so each entry is printed as a JSON line instead.
+#[derive(Default)]
+pub struct JsonOutputStrategy {
+    count: usize,
+    json_lines: bool,
+}
+
+impl JsonOutputStrategy {
+    pub fn create_from_options(option: &LogProcessorOptions) -> Self {
+        JsonOutputStrategy {
+            count: 0,
+            json_lines: option.follow,
+        }
+    }
+}
 
 impl OutputLogTrait for JsonOutputStrategy {
-    fn output(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
-        println!("{}", serde_json::to_string(logs).unwrap());
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        let mut stdout = io::stdout().lock();
+        if self.json_lines {
+            serde_json::to_writer(&mut stdout, log)
+                .map_err(|err| format!("failed to output : {}", err))?;
+            return writeln!(stdout).map_err(|err| format!("failed to output : {}", err));
+        }
+        let separator = if self.count == 0 { "[" } else { "," };
+        write!(stdout, "{}", separator).map_err(|err| format!("failed to output : {}", err))?;
+        serde_json::to_writer(&mut stdout, log)
+            .map_err(|err| format!("failed to output : {}", err))?;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if self.json_lines {
+            return Ok(());
+        }
+        if self.count == 0 {
+            print!("[");
+        }
+        println!("]");
 
         Ok(())
     }
@@ -4,12 +4,10 @@ use log::trace;
 pub struct JsonPrettyOutputStrategy;
 
 impl OutputLogTrait for JsonPrettyOutputStrategy {
-    fn output(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
         trace!("OutputLogTrait.output");
 
-        for ele in logs {
-            println!("{}", ele);
-        }
+        println!("{}", log);
         Ok(())
     }
 }
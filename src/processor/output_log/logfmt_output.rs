@@ -0,0 +1,113 @@
+use std::io::{self, Write};
+
+use serde_json::Value;
+
+use crate::processor::{
+    log_entry::{HTTPMethod, LogEntry},
+    log_trait::OutputLogTrait,
+};
+
+/// Prints one logfmt line per entry, with the same keys the logfmt decoder reads.
+pub struct LogfmtOutputStrategy;
+
+impl OutputLogTrait for LogfmtOutputStrategy {
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        writeln!(io::stdout().lock(), "{}", format_line(log))
+            .map_err(|err| format!("failed to output : {}", err))
+    }
+}
+
+fn format_line(log: &LogEntry) -> String {
+    let mut pairs: Vec<(&str, String)> = vec![
+        ("ts", log.timestamp.clone()),
+        ("level", log.level.as_str().to_lowercase()),
+        ("msg", log.message.clone()),
+    ];
+    if log.http_method != HTTPMethod::NONE {
+        pairs.push(("method", log.http_method.to_string()));
+    }
+    if !log.url.is_empty() {
+        pairs.push(("path", log.url.clone()));
+    }
+    if !log.route.is_empty() {
+        pairs.push(("route", log.route.clone()));
+    }
+    if let Some(status_code) = log.status_code {
+        pairs.push(("status", status_code.to_string()));
+    }
+    for (key, value) in [("ip", &log.ip_address), ("err", &log.error)] {
+        if !value.is_empty() {
+            pairs.push((key, value.clone()));
+        }
+    }
+    if log.process_time != 0f64 {
+        pairs.push(("dur", format!("{}ms", log.process_time)));
+    }
+    if let Some(source_file) = &log.source_file {
+        pairs.push(("source_file", source_file.clone()));
+    }
+    for (key, value) in &log.extra_fields {
+        // an extra field named like a written key would repeat it
+        if pairs.iter().any(|(written, _)| written == key) {
+            continue;
+        }
+        let value = match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        pairs.push((key, value));
+    }
+
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, quote(value)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn quote(value: &str) -> String {
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '=' || c == '\\')
+    {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::decode_log::logfmt_decode::decode_logfmt_line;
+
+    #[test]
+    fn test_logfmt_round_trip() {
+        let mut log = LogEntry::from_message("slow request".to_string());
+        log.url = "/users/42".to_string();
+        log.route = "/users/:id".to_string();
+        log.status_code = Some(200);
+        log.source_file = Some("/tmp/a.log".to_string());
+        log.extra_fields
+            .insert("trace_id".to_string(), Value::String("abc".to_string()));
+        let line = format_line(&log);
+
+        let decoded = decode_logfmt_line(&line).unwrap();
+        assert_eq!(decoded.route, "/users/:id");
+        assert_eq!(decoded.source_file.as_deref(), Some("/tmp/a.log"));
+        assert_eq!(decoded.extra_fields.len(), 1);
+        assert_eq!(format_line(&decoded), line);
+
+        // keys that clash with a written key are not repeated
+        let mut clashing = decoded;
+        clashing
+            .extra_fields
+            .insert("status".to_string(), Value::String("500".to_string()));
+        assert_eq!(format_line(&clashing), line);
+    }
+}
@@ -1,28 +1,99 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::processor::{log_entry::{HTTPMethod, LogEntry}, log_trait::OutputLogTrait};
+use crate::processor::{
+    log_entry::{HTTPMethod, LogEntry, LogLevel},
+    log_processor_options::LogProcessorOptions,
+    log_trait::OutputLogTrait,
+    quantile_sketch::QuantileSketch,
+};
 
-pub struct SummaryOutputStrategy;
+/// Number of client addresses listed in the summary.
+const TOP_CLIENTS: usize = 10;
+
+/// Aggregates entries as they arrive and prints the summary once the stream ends.
+#[derive(Default)]
+pub struct SummaryOutputStrategy {
+    count: usize,
+    first_timestamp: Option<String>,
+    last_timestamp: Option<String>,
+    method_counts: HashMap<HTTPMethod, usize>,
+    field_counts: BTreeMap<String, BTreeMap<String, usize>>,
+    clients: HashMap<String, ClientStats>,
+    routes: BTreeMap<String, EndpointStats>,
+    methods: BTreeMap<String, EndpointStats>,
+    status_classes: BTreeMap<String, usize>,
+}
+
+impl SummaryOutputStrategy {
+    pub fn create_from_options(option: &LogProcessorOptions) -> Self {
+        SummaryOutputStrategy {
+            field_counts: option
+                .summary_fields
+                .iter()
+                .map(|field| (field.clone(), BTreeMap::new()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
 
 impl OutputLogTrait for SummaryOutputStrategy {
-    fn output(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
-        let mut method_counts: HashMap<HTTPMethod, usize> = HashMap::new();
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        let method = &log.http_method;
+        let count = self.method_counts.entry(method.clone()).or_insert(0);
+        *count += 1;
 
-        for log in logs {
-            let method = &log.http_method;
-            let count = method_counts.entry(method.clone()).or_insert(0);
-            *count += 1;
+        for (field, counts) in &mut self.field_counts {
+            let value = log.field(field).unwrap_or_default();
+            *counts.entry(value).or_insert(0) += 1;
         }
 
+        if !log.ip_address.is_empty() {
+            let client = self.clients.entry(log.ip_address.clone()).or_default();
+            client.requests += 1;
+            if is_error(log) {
+                client.errors += 1;
+            }
+            client.total_latency_ms += log.process_time;
+        }
+        if !log.route.is_empty() {
+            self.routes.entry(log.route.clone()).or_default().add(log);
+        }
+        if log.http_method != HTTPMethod::NONE {
+            self.methods
+                .entry(log.http_method.to_string())
+                .or_default()
+                .add(log);
+        }
+        if let Some(status_code) = log.status_code {
+            let status_class = format!("{}xx", status_code / 100);
+            *self.status_classes.entry(status_class).or_insert(0) += 1;
+        }
+
+        self.count += 1;
+        if self.first_timestamp.is_none() {
+            self.first_timestamp = Some(log.timestamp.clone());
+        }
+        self.last_timestamp = Some(log.timestamp.clone());
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
         let summary = Summary {
-            count: logs.len(),
+            count: self.count,
             date_range: format!(
                 "{} - {}",
-                &logs.first().map_or("", |log| &log.timestamp),
-                &logs.last().map_or("", |log| &log.timestamp)
+                self.first_timestamp.as_deref().unwrap_or(""),
+                self.last_timestamp.as_deref().unwrap_or("")
             ),
-            http_method: method_counts,
+            http_method: std::mem::take(&mut self.method_counts),
+            fields: std::mem::take(&mut self.field_counts),
+            top_clients: top_clients(std::mem::take(&mut self.clients)),
+            latency_by_route: endpoint_summaries(&self.routes),
+            latency_by_method: endpoint_summaries(&self.methods),
+            status_classes: status_class_summaries(&self.status_classes),
         };
         println!("{}", serde_json::to_string_pretty(&summary).unwrap());
 
@@ -34,4 +105,237 @@ struct Summary {
     count: usize,
     date_range: String,
     http_method: HashMap<HTTPMethod, usize>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, BTreeMap<String, usize>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    top_clients: Vec<ClientSummary>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    latency_by_route: BTreeMap<String, EndpointSummary>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    latency_by_method: BTreeMap<String, EndpointSummary>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    status_classes: BTreeMap<String, StatusClassSummary>,
+}
+
+/// Latency sketch of `process_time` and request and error counts of one route or method.
+#[derive(Debug, Default)]
+struct EndpointStats {
+    requests: usize,
+    latency: QuantileSketch,
+    errors: usize,
+}
+
+impl EndpointStats {
+    fn add(&mut self, log: &LogEntry) {
+        self.requests += 1;
+        // 0 is the default of logs without a latency field, they would pull every
+        // percentile down to 0
+        if log.process_time > 0.0 {
+            self.latency.add(log.process_time);
+        }
+        if is_error(log) {
+            self.errors += 1;
+        }
+    }
+}
+
+/// Latencies in milliseconds of the `count` requests that had one, percentiles within 1%
+/// of the exact value. They are null when no request had a latency.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct EndpointSummary {
+    requests: usize,
+    count: u64,
+    mean: Option<f64>,
+    p50: Option<f64>,
+    p90: Option<f64>,
+    p95: Option<f64>,
+    p99: Option<f64>,
+    max: Option<f64>,
+    errors: usize,
+    error_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct StatusClassSummary {
+    count: usize,
+    rate: f64,
+}
+
+#[derive(Debug, Default)]
+struct ClientStats {
+    requests: usize,
+    errors: usize,
+    total_latency_ms: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct ClientSummary {
+    ip_address: String,
+    requests: usize,
+    errors: usize,
+    total_latency_ms: f64,
+}
+
+/// An error is a 4xx or 5xx response, or an error level for logs without a status.
+fn is_error(log: &LogEntry) -> bool {
+    match log.status_code {
+        Some(status_code) => status_code >= 400,
+        None => log.level == LogLevel::ERROR,
+    }
+}
+
+fn endpoint_summaries(
+    endpoints: &BTreeMap<String, EndpointStats>,
+) -> BTreeMap<String, EndpointSummary> {
+    endpoints
+        .iter()
+        .map(|(endpoint, stats)| {
+            let latency = &stats.latency;
+            let quantile = |q: f64| latency.quantile(q).map(round);
+            let summary = EndpointSummary {
+                requests: stats.requests,
+                count: latency.count(),
+                mean: latency.mean().map(round),
+                p50: quantile(0.5),
+                p90: quantile(0.9),
+                p95: quantile(0.95),
+                p99: quantile(0.99),
+                max: latency.max().map(round),
+                errors: stats.errors,
+                error_rate: rate(stats.errors, stats.requests),
+            };
+            (endpoint.clone(), summary)
+        })
+        .collect()
+}
+
+/// Share of each status class among the logs that have a status code.
+fn status_class_summaries(
+    status_classes: &BTreeMap<String, usize>,
+) -> BTreeMap<String, StatusClassSummary> {
+    let total: usize = status_classes.values().sum();
+    status_classes
+        .iter()
+        .map(|(status_class, count)| {
+            let summary = StatusClassSummary {
+                count: *count,
+                rate: rate(*count, total),
+            };
+            (status_class.clone(), summary)
+        })
+        .collect()
+}
+
+fn rate(count: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        total => round(count as f64 / total as f64),
+    }
+}
+
+/// Rounds to 3 decimals, enough for milliseconds and rates in the printed summary.
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// The clients with the most requests, ties go to the most errors, then by address.
+fn top_clients(clients: HashMap<String, ClientStats>) -> Vec<ClientSummary> {
+    let mut clients: Vec<ClientSummary> = clients
+        .into_iter()
+        .map(|(ip_address, stats)| ClientSummary {
+            ip_address,
+            requests: stats.requests,
+            errors: stats.errors,
+            total_latency_ms: stats.total_latency_ms,
+        })
+        .collect();
+    clients.sort_by(|a, b| {
+        b.requests
+            .cmp(&a.requests)
+            .then(b.errors.cmp(&a.errors))
+            .then(a.ip_address.cmp(&b.ip_address))
+    });
+    clients.truncate(TOP_CLIENTS);
+    clients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip_address: &str, status_code: u16, process_time: f64) -> LogEntry {
+        let mut log = LogEntry::from_message(String::new());
+        log.ip_address = ip_address.to_string();
+        log.status_code = Some(status_code);
+        log.process_time = process_time;
+        log
+    }
+
+    #[test]
+    fn test_latency_and_status_classes() {
+        let mut summary = SummaryOutputStrategy::default();
+        for i in 1..=100 {
+            let mut log = entry("10.0.0.1", if i % 10 == 0 { 500 } else { 200 }, i as f64);
+            log.route = "/users/:id".to_string();
+            log.http_method = HTTPMethod::GET;
+            summary.output(&log).unwrap();
+        }
+        for _ in 0..100 {
+            // no latency field
+            let mut log = entry("10.0.0.1", 200, 0.0);
+            log.route = "/users/:id".to_string();
+            summary.output(&log).unwrap();
+        }
+        let routes = endpoint_summaries(&summary.routes);
+        let route = &routes["/users/:id"];
+        assert_eq!(route.requests, 200);
+        assert_eq!(route.count, 100);
+        assert_eq!(route.mean, Some(50.5));
+        assert_eq!(route.max, Some(100.0));
+        let p90 = route.p90.unwrap();
+        assert!((p90 - 90.0).abs() <= 2.0, "p90 = {}", p90);
+        assert_eq!(route.errors, 10);
+        assert_eq!(route.error_rate, 0.05);
+        assert_eq!(endpoint_summaries(&summary.methods)["GET"].count, 100);
+
+        let status_classes = status_class_summaries(&summary.status_classes);
+        assert_eq!(
+            status_classes["5xx"],
+            StatusClassSummary {
+                count: 10,
+                rate: 0.05
+            }
+        );
+        assert_eq!(status_classes["2xx"].count, 190);
+    }
+
+    #[test]
+    fn test_top_clients() {
+        let mut summary = SummaryOutputStrategy::default();
+        for log in [
+            entry("10.0.0.2", 200, 5.0),
+            entry("10.0.0.1", 503, 10.0),
+            entry("10.0.0.1", 200, 2.5),
+            entry("", 200, 1.0),
+        ] {
+            summary.output(&log).unwrap();
+        }
+        assert_eq!(
+            top_clients(std::mem::take(&mut summary.clients)),
+            vec![
+                ClientSummary {
+                    ip_address: "10.0.0.1".to_string(),
+                    requests: 2,
+                    errors: 1,
+                    total_latency_ms: 12.5,
+                },
+                ClientSummary {
+                    ip_address: "10.0.0.2".to_string(),
+                    requests: 1,
+                    errors: 0,
+                    total_latency_ms: 5.0,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::processor::{
+    log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::OutputLogTrait,
+};
+
+/// Shown for logs that have no value for the field.
+const NO_VALUE: &str = "(none)";
+
+/// Counts the values of one field and prints the most frequent ones with their share of
+/// all logs. With a group-by field the values are ranked, and their share taken, within
+/// each group, and the groups are ordered by their number of logs.
+pub struct TopOutputStrategy {
+    field: String,
+    group_by: Option<String>,
+    limit: usize,
+    count: usize,
+    groups: HashMap<String, HashMap<String, usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct TopGroup {
+    group: String,
+    count: usize,
+    values: Vec<(String, usize)>,
+    other: usize,
+}
+
+impl TopOutputStrategy {
+    pub fn create_from_options(option: &LogProcessorOptions) -> Self {
+        TopOutputStrategy {
+            field: option.top_field.clone().unwrap_or_default(),
+            group_by: option.top_group_by.clone(),
+            limit: option.top_count,
+            count: 0,
+            groups: HashMap::new(),
+        }
+    }
+
+    fn top_groups(&self) -> Vec<TopGroup> {
+        let mut groups: Vec<TopGroup> = self
+            .groups
+            .iter()
+            .map(|(group, counts)| {
+                let count = counts.values().sum();
+                let values = top_values(counts, self.limit);
+                let other = count - values.iter().map(|(_, count)| count).sum::<usize>();
+                TopGroup {
+                    group: group.clone(),
+                    count,
+                    values,
+                    other,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| b.count.cmp(&a.count).then(a.group.cmp(&b.group)));
+        groups
+    }
+
+    fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        let groups = self.top_groups();
+        let count_width = self.count.to_string().len().max("count".len());
+        for (i, group) in groups.iter().enumerate() {
+            if let Some(group_by) = &self.group_by {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(
+                    out,
+                    "{} = {} ({}, {})",
+                    group_by,
+                    group.group,
+                    group.count,
+                    percent(group.count, self.count)
+                )?;
+            }
+            writeln!(
+                out,
+                "{:>count_width$} {:>7}  {}",
+                "count", "percent", self.field
+            )?;
+            let other = (group.other > 0).then(|| ("(other)".to_string(), group.other));
+            for (value, count) in group.values.iter().chain(other.as_ref()) {
+                writeln!(
+                    out,
+                    "{:>count_width$} {:>7}  {}",
+                    count,
+                    percent(*count, group.count),
+                    value
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OutputLogTrait for TopOutputStrategy {
+    fn output(&mut self, log: &LogEntry) -> Result<(), String> {
+        let group = match &self.group_by {
+            Some(group_by) => field_value(log, group_by),
+            None => String::new(),
+        };
+        let value = field_value(log, &self.field);
+        *self
+            .groups
+            .entry(group)
+            .or_default()
+            .entry(value)
+            .or_insert(0) += 1;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.write_table(&mut io::stdout().lock())
+            .map_err(|err| format!("failed to output : {}", err))
+    }
+}
+
+fn field_value(log: &LogEntry, field: &str) -> String {
+    log.field(field)
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| NO_VALUE.to_string())
+}
+
+/// The `limit` most frequent values, ties in value order so the output is stable.
+fn top_values(counts: &HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut values: Vec<(String, usize)> = counts
+        .iter()
+        .map(|(value, count)| (value.clone(), *count))
+        .collect();
+    values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    values.truncate(limit);
+    values
+}
+
+fn percent(count: usize, total: usize) -> String {
+    match total {
+        0 => "-".to_string(),
+        total => format!("{:.1}%", count as f64 * 100.0 / total as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top(field: &str, group_by: Option<&str>, limit: usize) -> TopOutputStrategy {
+        let mut top = TopOutputStrategy {
+            field: field.to_string(),
+            group_by: group_by.map(str::to_string),
+            limit,
+            count: 0,
+            groups: HashMap::new(),
+        };
+        for (url, status_code) in [
+            ("/a", Some(200)),
+            ("/a", Some(200)),
+            ("/a", Some(500)),
+            ("/b", Some(404)),
+            ("/c", None),
+        ] {
+            let mut log = LogEntry::from_message(String::new());
+            log.url = url.to_string();
+            log.status_code = status_code;
+            top.output(&log).unwrap();
+        }
+        top
+    }
+
+    #[test]
+    fn test_top_values() {
+        let top = top("status_code", None, 2);
+        let mut table = Vec::new();
+        top.write_table(&mut table).unwrap();
+        assert_eq!(
+            String::from_utf8(table).unwrap(),
+            "count percent  status_code\n\
+             \x20   2   40.0%  200\n\
+             \x20   1   20.0%  (none)\n\
+             \x20   2   40.0%  (other)\n"
+        );
+    }
+
+    #[test]
+    fn test_top_values_grouped() {
+        let groups = top("status_code", Some("url"), 10).top_groups();
+        assert_eq!(
+            groups[0],
+            TopGroup {
+                group: "/a".to_string(),
+                count: 3,
+                values: vec![("200".to_string(), 2), ("500".to_string(), 1)],
+                other: 0,
+            }
+        );
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| group.group.as_str())
+                .collect::<Vec<_>>(),
+            ["/a", "/b", "/c"]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+/// Relative error of the quantiles, 1% keeps a few hundred buckets for latencies from
+/// microseconds to hours.
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Streaming quantile sketch with a bounded relative error, after DDSketch. Values go into
+/// logarithmic buckets, so memory depends on the spread of the values and not on how many
+/// there are. Count, mean, min and max are exact. Values must not be negative.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    gamma_ln: f64,
+    buckets: BTreeMap<i32, u64>,
+    zero_count: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        let gamma = (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY);
+        QuantileSketch {
+            gamma_ln: gamma.ln(),
+            buckets: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl QuantileSketch {
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() || value < 0.0 {
+            return;
+        }
+        if value == 0.0 {
+            self.zero_count += 1;
+        } else {
+            let index = (value.ln() / self.gamma_ln).ceil() as i32;
+            *self.buckets.entry(index).or_insert(0) += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// The value at quantile `q` in 0..=1, within the relative accuracy of the true value.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if q >= 1.0 {
+            return Some(self.max);
+        }
+        // nearest rank: the smallest value with at least a share `q` of values at or below it
+        let rank = ((q.max(0.0) * self.count as f64).ceil() as u64).saturating_sub(1);
+        if rank < self.zero_count {
+            return Some(0.0);
+        }
+        let mut seen = self.zero_count;
+        for (index, count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                // midpoint of the bucket, which holds the values in (gamma^(i-1), gamma^i]
+                let value =
+                    2.0 * (*index as f64 * self.gamma_ln).exp() / (1.0 + self.gamma_ln.exp());
+                return Some(value.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_accuracy() {
+        let mut sketch = QuantileSketch::default();
+        for value in 1..=10_000 {
+            sketch.add(value as f64);
+        }
+        assert_eq!(sketch.count(), 10_000);
+        assert_eq!(sketch.mean(), Some(5000.5));
+        assert_eq!(sketch.max(), Some(10_000.0));
+        for (q, expected) in [(0.5, 5000.0), (0.9, 9000.0), (0.99, 9900.0)] {
+            let value = sketch.quantile(q).unwrap();
+            assert!(
+                (value - expected).abs() / expected <= 2.0 * RELATIVE_ACCURACY,
+                "p{} = {}",
+                q * 100.0,
+                value
+            );
+        }
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(1.0), Some(10_000.0));
+    }
+
+    #[test]
+    fn test_zero_and_empty() {
+        let mut sketch = QuantileSketch::default();
+        assert_eq!(sketch.quantile(0.5), None);
+        assert_eq!(sketch.mean(), None);
+
+        sketch.add(0.0);
+        sketch.add(0.0);
+        sketch.add(8.0);
+        sketch.add(-1.0);
+        assert_eq!(sketch.count(), 3);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert_eq!(sketch.quantile(1.0), Some(8.0));
+    }
+}
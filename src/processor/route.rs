@@ -0,0 +1,205 @@
+use log::debug;
+
+/// Placeholder for path segments that are identifiers rather than part of the endpoint.
+pub const ID_PLACEHOLDER: &str = ":id";
+
+/// Shortest run of hex digits taken for a hash or object id, e.g. a 24 digit Mongo id.
+const MIN_HASH_LEN: usize = 16;
+
+#[derive(Debug, Clone)]
+enum TemplateSegment {
+    Literal(String),
+    Param,
+    Rest,
+}
+
+/// A `--route` template like `/users/:user_id/orders/:id` or `/static/*`. `:name` matches
+/// one path segment and a trailing `*` matches the rest of the path.
+#[derive(Debug, Clone)]
+struct RouteTemplate {
+    template: String,
+    segments: Vec<TemplateSegment>,
+}
+
+impl RouteTemplate {
+    fn parse(template: &str) -> Result<Self, String> {
+        let template = template.trim();
+        if !template.starts_with('/') {
+            return Err(format!(
+                "Failed to parse route `{}`, expected a path starting with /",
+                template
+            ));
+        }
+        let parts: Vec<&str> = path_segments(template).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            segments.push(match *part {
+                "*" if i == parts.len() - 1 => TemplateSegment::Rest,
+                "*" => {
+                    return Err(format!(
+                        "Failed to parse route `{}`, * is only allowed at the end",
+                        template
+                    ))
+                }
+                part if part.starts_with(':') => TemplateSegment::Param,
+                part => TemplateSegment::Literal(part.to_string()),
+            });
+        }
+        Ok(RouteTemplate {
+            template: template.to_string(),
+            segments,
+        })
+    }
+
+    fn matches(&self, path: &[&str]) -> bool {
+        let mut path = path.iter();
+        for segment in &self.segments {
+            match (segment, path.next()) {
+                (TemplateSegment::Rest, _) => return true,
+                (TemplateSegment::Param, Some(_)) => {}
+                (TemplateSegment::Literal(literal), Some(part)) if literal == part => {}
+                _ => return false,
+            }
+        }
+        path.next().is_none()
+    }
+}
+
+/// Turns the raw `url` into the endpoint it belongs to, so `/users/123` and `/users/456`
+/// both become `/users/:id`. The first matching `--route` template wins, otherwise numbers,
+/// UUIDs and hashes are replaced by `:id`. Query strings are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct RouteNormalizer {
+    templates: Vec<RouteTemplate>,
+}
+
+impl RouteNormalizer {
+    pub fn create(templates: &[String]) -> Result<Self, String> {
+        let templates = templates
+            .iter()
+            .map(|template| RouteTemplate::parse(template))
+            .collect::<Result<Vec<RouteTemplate>, String>>()?;
+        debug!(
+            "routes: {:?}",
+            templates
+                .iter()
+                .map(|template| &template.template)
+                .collect::<Vec<_>>()
+        );
+        Ok(RouteNormalizer { templates })
+    }
+
+    pub fn normalize(&self, url: &str) -> String {
+        let path = strip_url(url);
+        if path.is_empty() {
+            return String::new();
+        }
+        let segments: Vec<&str> = path_segments(path).collect();
+        if let Some(template) = self
+            .templates
+            .iter()
+            .find(|template| template.matches(&segments))
+        {
+            return template.template.clone();
+        }
+
+        let mut route = String::with_capacity(path.len());
+        for segment in &segments {
+            route.push('/');
+            route.push_str(if is_identifier(segment) {
+                ID_PLACEHOLDER
+            } else {
+                segment
+            });
+        }
+        if route.is_empty() || path.ends_with('/') {
+            route.push('/');
+        }
+        route
+    }
+}
+
+/// The path of `url`, without scheme, host, query string or fragment.
+fn strip_url(url: &str) -> &str {
+    let url = url.trim();
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |start| &rest[start..]),
+        None => url,
+    }
+}
+
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn is_identifier(segment: &str) -> bool {
+    is_number(segment) || is_uuid(segment) || is_hash(segment)
+}
+
+fn is_number(segment: &str) -> bool {
+    segment.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn is_uuid(segment: &str) -> bool {
+    segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Long runs of hex digits with at least one digit, so words made only of the letters a-f
+/// are kept.
+fn is_hash(segment: &str) -> bool {
+    segment.len() >= MIN_HASH_LEN
+        && segment.bytes().all(|byte| byte.is_ascii_hexdigit())
+        && segment.bytes().any(|byte| byte.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_identifiers() {
+        let routes = RouteNormalizer::default();
+        assert_eq!(routes.normalize("/users/123"), "/users/:id");
+        assert_eq!(
+            routes.normalize("/users/456/orders?page=2"),
+            "/users/:id/orders"
+        );
+        assert_eq!(
+            routes.normalize("/files/3f2504e0-4f89-11d3-9a0c-0305e82c3301/raw"),
+            "/files/:id/raw"
+        );
+        assert_eq!(
+            routes.normalize("/commits/9b2e1f0c4d5a6b7c8d9e0f1a2b3c4d5e6f708192"),
+            "/commits/:id"
+        );
+        assert_eq!(
+            routes.normalize("https://example.com/v2/items/"),
+            "/v2/items/"
+        );
+        assert_eq!(routes.normalize("/"), "/");
+        assert_eq!(routes.normalize(""), "");
+    }
+
+    #[test]
+    fn test_normalize_templates() {
+        let routes = RouteNormalizer::create(&[
+            "/users/:user_id/repos/:repo".to_string(),
+            "/static/*".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            routes.normalize("/users/octocat/repos/hello-world"),
+            "/users/:user_id/repos/:repo"
+        );
+        assert_eq!(routes.normalize("/static/css/site.css"), "/static/*");
+        assert_eq!(routes.normalize("/users/octocat"), "/users/octocat");
+
+        assert!(RouteNormalizer::create(&["users/:id".to_string()]).is_err());
+        assert!(RouteNormalizer::create(&["/a/*/b".to_string()]).is_err());
+    }
+}
@@ -3,12 +3,20 @@ use log::debug;
 use serde_json::{json, Value};
 use tokio::runtime::Runtime;
 
-use crate::processor::{db::EsClient, log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::SaveLogTrait};
+use crate::processor::{
+    db::EsClient, log_entry::LogEntry, log_processor_options::LogProcessorOptions,
+    log_trait::SaveLogTrait,
+};
+
+const CHUNK_SIZE: usize = 1000;
 
 pub struct ESSaveStrategy<'a> {
     index: String,
     truncate_on_save: bool,
     es_client: &'a EsClient,
+    runtime: Runtime,
+    ops: Vec<BulkOperation<Value>>,
+    truncated: bool,
 }
 
 impl<'a> ESSaveStrategy<'a> {
@@ -17,78 +25,91 @@ impl<'a> ESSaveStrategy<'a> {
         option: LogProcessorOptions,
         es_client: &'a EsClient,
     ) -> Result<Self, String> {
+        let runtime =
+            Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
         Ok(ESSaveStrategy {
             index,
             truncate_on_save: option.truncate_on_save,
             es_client,
+            runtime,
+            ops: Vec::with_capacity(CHUNK_SIZE),
+            truncated: false,
         })
     }
-}
-
-impl<'a> SaveLogTrait for ESSaveStrategy<'a> {
-    fn save(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
-        let runtime =
-            Runtime::new().map_err(|err| format!("failed creating worker thread : {}", err))?;
-        let result: Result<(), String> = runtime.block_on(async {
-            if self.truncate_on_save {
-                let es_search_body = json!({
-                  "query": {
-                    "match_all": {}
-                  },
-
-                });
-                let response = self
-                    .es_client
-                    .client
-                    .delete_by_query(elasticsearch::DeleteByQueryParts::Index(&[self
-                        .index
-                        .as_str()]))
-                    .body(es_search_body)
-                    .send()
-                    .await
-                    .map_err(|err| format!("Failed opening file: {}", err))?;
 
-                let response = response
-                    .text()
-                    .await
-                    .map_err(|err| format!("Failed to get response text: {}", err))?;
-                debug!("truncate_on_save response: {}", response);
-            }
-            let chunk_size = 1000;
+    async fn truncate(&self) -> Result<(), String> {
+        let es_search_body = json!({
+          "query": {
+            "match_all": {}
+          },
 
-            let chunked_logs: Vec<Vec<LogEntry>> = logs
-                .chunks(chunk_size)
-                .map(|chunk| chunk.to_vec())
-                .collect();
+        });
+        let response = self
+            .es_client
+            .client
+            .delete_by_query(elasticsearch::DeleteByQueryParts::Index(&[self
+                .index
+                .as_str()]))
+            .body(es_search_body)
+            .send()
+            .await
+            .map_err(|err| format!("Failed opening file: {}", err))?;
 
-            for chunk in chunked_logs {
-                let mut ops: Vec<BulkOperation<Value>> = Vec::with_capacity(chunk_size);
+        let response = response
+            .text()
+            .await
+            .map_err(|err| format!("Failed to get response text: {}", err))?;
+        debug!("truncate_on_save response: {}", response);
+        Ok(())
+    }
 
-                for log in &chunk {
-                    let value = serde_json::to_value(&log).unwrap();
-                    ops.push(BulkOperation::create(log.timestamp.clone(), value).into());
-                }
+    async fn bulk(&self, ops: Vec<BulkOperation<Value>>) -> Result<(), String> {
+        let response = self
+            .es_client
+            .client
+            .bulk(BulkParts::Index(self.index.as_ref()))
+            .body(ops)
+            .send()
+            .await
+            .map_err(|err| format!("{}", err))?;
 
-                let response = self
-                    .es_client
-                    .client
-                    .bulk(BulkParts::Index(self.index.as_ref()))
-                    .body(ops)
-                    .send()
-                    .await
-                    .map_err(|err| format!("{}", err))?;
+        let response = response
+            .text()
+            .await
+            .map_err(|err| format!("Failed to get response text: {}", err))?;
+        debug!("response: {}", response);
+        Ok(())
+    }
 
-                let response = response
-                    .text()
-                    .await
-                    .map_err(|err| format!("Failed to get response text: {}", err))?;
-                debug!("response: {}", response);
+    /// Sends the buffered chunk, truncating the index first when this is the first write.
+    fn flush_chunk(&mut self) -> Result<(), String> {
+        let ops = std::mem::replace(&mut self.ops, Vec::with_capacity(CHUNK_SIZE));
+        let truncate = self.truncate_on_save && !self.truncated;
+        self.truncated = true;
+        self.runtime.block_on(async {
+            if truncate {
+                self.truncate().await?;
+            }
+            if !ops.is_empty() {
+                self.bulk(ops).await?;
             }
-
             Ok(())
-        });
-        result?;
+        })
+    }
+}
 
+impl<'a> SaveLogTrait for ESSaveStrategy<'a> {
+    fn save(&mut self, log: &LogEntry) -> Result<(), String> {
+        let value = serde_json::to_value(log).map_err(|err| format!("{}", err))?;
+        self.ops
+            .push(BulkOperation::create(log.timestamp.clone(), value).into());
+        if self.ops.len() >= CHUNK_SIZE {
+            self.flush_chunk()?;
+        }
         Ok(())
     }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.flush_chunk()
+    }
 }
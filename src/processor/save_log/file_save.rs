@@ -1,9 +1,16 @@
-use std::{fs, io::Write, path::PathBuf};
-use crate::processor::{log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::SaveLogTrait};
+use crate::processor::{
+    log_entry::LogEntry, log_processor_options::LogProcessorOptions, log_trait::SaveLogTrait,
+};
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
 
 pub struct FileSaveStrategy {
     path: PathBuf,
     truncate_on_save: bool,
+    writer: Option<BufWriter<fs::File>>,
 }
 
 impl FileSaveStrategy {
@@ -11,20 +18,45 @@ impl FileSaveStrategy {
         Ok(FileSaveStrategy {
             path,
             truncate_on_save: option.truncate_on_save,
+            writer: None,
         })
     }
+
+    /// Opens the file on first use and writes the opening bracket of the JSON array.
+    fn writer(&mut self) -> Result<(&mut BufWriter<fs::File>, bool), String> {
+        let is_first = self.writer.is_none();
+        if is_first {
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(self.truncate_on_save)
+                .open(self.path.clone())
+                .map_err(|err| format!("failed to open file : {}", err))?;
+            let mut writer = BufWriter::new(file);
+            write!(writer, "[").map_err(|err| format!("failed to save file : {}", err))?;
+            self.writer = Some(writer);
+        }
+        Ok((self.writer.as_mut().unwrap(), is_first))
+    }
 }
 
 impl SaveLogTrait for FileSaveStrategy {
-    fn save(&self, logs: &Vec<LogEntry>) -> Result<(), String> {
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(self.truncate_on_save)
-            .open(self.path.clone())
-            .map_err(|err| format!("failed to open file : {}", err))?;
+    fn save(&mut self, log: &LogEntry) -> Result<(), String> {
+        let (writer, is_first) = self.writer()?;
+        if !is_first {
+            write!(writer, ",").map_err(|err| format!("failed to save file : {}", err))?;
+        }
+        serde_json::to_writer(&mut *writer, log)
+            .map_err(|err| format!("failed to save file : {}", err))?;
+
+        Ok(())
+    }
 
-        write!(file, "{}", serde_json::to_string(logs).unwrap())
+    fn finish(&mut self) -> Result<(), String> {
+        let (writer, _) = self.writer()?;
+        write!(writer, "]").map_err(|err| format!("failed to save file : {}", err))?;
+        writer
+            .flush()
             .map_err(|err| format!("failed to save file : {}", err))?;
 
         Ok(())
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+
+use super::{duration::parse_duration_ms, timestamp::DefaultTimezone};
+
+/// Time range of the logs to keep, in unix milliseconds like `LogEntry::time_unix`. The
+/// start is inclusive and the end exclusive, either may be open.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl TimeRange {
+    /// Builds the range of `--since` and `--until`. Each accepts an RFC 3339 instant, a date
+    /// or date and time in `timezone`, `now`, `today`, `yesterday`, or a duration ago like
+    /// `15m`, `2h` or `3d`. A plain date given to `--until` includes that whole day.
+    pub fn from_since_until(
+        since: Option<&str>,
+        until: Option<&str>,
+        timezone: &DefaultTimezone,
+        now: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        let start = since
+            .map(|since| parse_time(since, timezone, now, false))
+            .transpose()
+            .map_err(|err| format!("Failed to parse --since: {}", err))?;
+        let end = until
+            .map(|until| parse_time(until, timezone, now, true))
+            .transpose()
+            .map_err(|err| format!("Failed to parse --until: {}", err))?;
+        let range = TimeRange { start, end };
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Err(format!("--since must be before --until, got {}", range));
+            }
+        }
+        Ok(range)
+    }
+
+    /// Builds the range of `--date-filter`: `today` (or `0`), `yesterday`, `N-` for the last
+    /// N days including today, or `A_B` for the hours A to B of today.
+    pub fn from_date_filter(
+        date_filter: &str,
+        timezone: &DefaultTimezone,
+        now: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        let today = timezone.local_date(now);
+        let day_range = |first: NaiveDate, last: NaiveDate| -> Result<Self, String> {
+            Ok(TimeRange {
+                start: Some(start_of_day(first, timezone)?),
+                end: Some(start_of_day(next_day(last)?, timezone)?),
+            })
+        };
+        match date_filter {
+            "today" | "0" => day_range(today, today),
+            "yesterday" => {
+                let yesterday = previous_days(today, 1)?;
+                day_range(yesterday, yesterday)
+            }
+            n_dash if n_dash.ends_with('-') => {
+                let days = n_dash
+                    .trim_end_matches('-')
+                    .parse::<u64>()
+                    .map_err(|_| "Failed to parse date filter: N- needs a number of days")?;
+                day_range(previous_days(today, days)?, today)
+            }
+            a_b if a_b.contains('_') => {
+                let hours = a_b
+                    .split_once('_')
+                    .and_then(|(a, b)| Some((a.parse::<u32>().ok()?, b.parse::<u32>().ok()?)))
+                    .filter(|(a, b)| a < b && *b <= 24)
+                    .ok_or("Failed to parse date filter: A_B needs hours with 0 <= A < B <= 24")?;
+                let hour = |hour: u32| -> Result<i64, String> {
+                    let time =
+                        today.and_time(NaiveTime::MIN) + chrono::Duration::hours(i64::from(hour));
+                    to_millis(&time, timezone)
+                };
+                Ok(TimeRange {
+                    start: Some(hour(hours.0)?),
+                    end: Some(hour(hours.1)?),
+                })
+            }
+            _ => Err("Failed to parse date filter".to_string()),
+        }
+    }
+
+    pub fn contains(&self, time_unix: i64) -> bool {
+        self.start.is_none_or(|start| time_unix >= start)
+            && self.end.is_none_or(|end| time_unix < end)
+    }
+
+    pub fn to_es_query(self) -> Value {
+        let mut range = json!({});
+        if let Some(start) = self.start {
+            range["gte"] = json!(start);
+        }
+        if let Some(end) = self.end {
+            range["lt"] = json!(end);
+        }
+        json!({"range": {"time_unix": range}})
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |time: Option<i64>| {
+            time.and_then(DateTime::from_timestamp_millis)
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        write!(f, "{} .. {}", bound(self.start), bound(self.end))
+    }
+}
+
+fn parse_time(
+    value: &str,
+    timezone: &DefaultTimezone,
+    now: DateTime<Utc>,
+    is_end: bool,
+) -> Result<i64, String> {
+    let value = value.trim();
+    let today = timezone.local_date(now);
+    // like a plain date, `today` and `yesterday` include that whole day as an end
+    let day_bound = |date: NaiveDate| {
+        let date = if is_end { next_day(date)? } else { date };
+        start_of_day(date, timezone)
+    };
+    match value {
+        "now" => return Ok(now.timestamp_millis()),
+        "today" => return day_bound(today),
+        "yesterday" => return day_bound(previous_days(today, 1)?),
+        _ => {}
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp_millis());
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return to_millis(&time, timezone);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return day_bound(date);
+    }
+    if let Some(ago) = parse_duration_ms(value) {
+        return Ok(now.timestamp_millis() - ago as i64);
+    }
+    Err(format!(
+        "`{}` is not a time, expected e.g. 2024-03-01T10:00:00Z, 2024-03-01, 2024-03-01 10:00, 15m, 2h or 3d",
+        value
+    ))
+}
+
+fn start_of_day(date: NaiveDate, timezone: &DefaultTimezone) -> Result<i64, String> {
+    to_millis(&date.and_time(NaiveTime::MIN), timezone)
+}
+
+fn to_millis(time: &NaiveDateTime, timezone: &DefaultTimezone) -> Result<i64, String> {
+    timezone
+        .to_millis(time)
+        .ok_or_else(|| format!("{} doesn't exist in timezone {:?}", time, timezone))
+}
+
+fn next_day(date: NaiveDate) -> Result<NaiveDate, String> {
+    date.checked_add_days(Days::new(1))
+        .ok_or_else(|| format!("date out of range after {}", date))
+}
+
+fn previous_days(date: NaiveDate, days: u64) -> Result<NaiveDate, String> {
+    date.checked_sub_days(Days::new(days))
+        .ok_or_else(|| format!("date out of range {} days before {}", days, date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        "2024-03-01T10:30:00Z".parse().unwrap()
+    }
+
+    fn millis(time: &str) -> Option<i64> {
+        Some(time.parse::<DateTime<Utc>>().unwrap().timestamp_millis())
+    }
+
+    #[test]
+    fn test_since_until() {
+        let utc = DefaultTimezone::parse("UTC").unwrap();
+        let range = TimeRange::from_since_until(Some("2h"), Some("2024-03-01"), &utc, now());
+        assert_eq!(
+            range,
+            Ok(TimeRange {
+                start: millis("2024-03-01T08:30:00Z"),
+                end: millis("2024-03-02T00:00:00Z"),
+            })
+        );
+
+        let berlin = DefaultTimezone::parse("Europe/Berlin").unwrap();
+        let range =
+            TimeRange::from_since_until(Some("2024-03-01 09:00"), None, &berlin, now()).unwrap();
+        assert_eq!(range.start, millis("2024-03-01T08:00:00Z"));
+        assert!(range.contains(millis("2024-03-01T08:00:00Z").unwrap()));
+        assert!(!range.contains(millis("2024-03-01T07:59:59Z").unwrap()));
+
+        assert_eq!(
+            TimeRange::from_since_until(Some("today"), Some("today"), &utc, now()),
+            Ok(TimeRange {
+                start: millis("2024-03-01T00:00:00Z"),
+                end: millis("2024-03-02T00:00:00Z"),
+            })
+        );
+        assert_eq!(
+            TimeRange::from_since_until(None, Some("yesterday"), &utc, now())
+                .unwrap()
+                .end,
+            millis("2024-03-01T00:00:00Z")
+        );
+
+        assert!(TimeRange::from_since_until(Some("soon"), None, &utc, now()).is_err());
+        assert!(TimeRange::from_since_until(Some("1h"), Some("2h"), &utc, now()).is_err());
+    }
+
+    #[test]
+    fn test_date_filter() {
+        let utc = DefaultTimezone::parse("UTC").unwrap();
+        assert_eq!(
+            TimeRange::from_date_filter("yesterday", &utc, now()),
+            Ok(TimeRange {
+                start: millis("2024-02-29T00:00:00Z"),
+                end: millis("2024-03-01T00:00:00Z"),
+            })
+        );
+        assert_eq!(
+            TimeRange::from_date_filter("8_17", &utc, now()),
+            Ok(TimeRange {
+                start: millis("2024-03-01T08:00:00Z"),
+                end: millis("2024-03-01T17:00:00Z"),
+            })
+        );
+        assert_eq!(
+            TimeRange::from_date_filter("2-", &utc, now())
+                .unwrap()
+                .start,
+            millis("2024-02-28T00:00:00Z")
+        );
+        assert!(TimeRange::from_date_filter("17_8", &utc, now()).is_err());
+        assert!(TimeRange::from_date_filter("x-", &utc, now()).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::debug;
+use std::cell::{Cell, RefCell};
+
+use super::{log_entry::LogEntry, log_processor_options::LogProcessorOptions};
+
+/// strptime formats tried after RFC 3339, epoch numbers and the `--timestamp-format` list.
+const BUILTIN_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f%:z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S,%3f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%d/%b/%Y:%H:%M:%S %z",
+    "%d/%b/%Y %H:%M:%S",
+    "%b %e %H:%M:%S %Y",
+];
+
+/// Timezone given to timestamps that don't carry an offset, UTC unless `--timezone` is
+/// given.
+#[derive(Debug, Clone)]
+pub enum DefaultTimezone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for DefaultTimezone {
+    fn default() -> Self {
+        DefaultTimezone::Named(Tz::UTC)
+    }
+}
+
+impl DefaultTimezone {
+    /// Parses `local`, an offset like `+02:00`, or an IANA name like `UTC` or
+    /// `Europe/Berlin`.
+    pub fn parse(timezone: &str) -> Result<Self, String> {
+        if timezone.eq_ignore_ascii_case("local") {
+            return Ok(DefaultTimezone::Local);
+        }
+        if let Ok(offset) = timezone.parse::<FixedOffset>() {
+            return Ok(DefaultTimezone::Fixed(offset));
+        }
+        timezone
+            .parse::<Tz>()
+            .map(DefaultTimezone::Named)
+            .map_err(|_| format!("Unknown timezone `{}`", timezone))
+    }
+
+    /// Reads a date and time without offset in this timezone, as unix milliseconds.
+    pub fn to_millis(&self, time: &NaiveDateTime) -> Option<i64> {
+        let time = match self {
+            DefaultTimezone::Local => Local.from_local_datetime(time).earliest()?.fixed_offset(),
+            DefaultTimezone::Fixed(offset) => offset.from_local_datetime(time).earliest()?,
+            DefaultTimezone::Named(tz) => tz.from_local_datetime(time).earliest()?.fixed_offset(),
+        };
+        Some(time.timestamp_millis())
+    }
+
+    /// The calendar date at `time` in this timezone.
+    pub fn local_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        match self {
+            DefaultTimezone::Local => time.with_timezone(&Local).date_naive(),
+            DefaultTimezone::Fixed(offset) => time.with_timezone(offset).date_naive(),
+            DefaultTimezone::Named(tz) => time.with_timezone(tz).date_naive(),
+        }
+    }
+}
+
+/// Turns the `timestamp` text of an entry into `time_unix` milliseconds, and counts the
+/// entries whose timestamp could not be parsed.
+pub struct TimestampParser {
+    formats: Vec<String>,
+    timezone: DefaultTimezone,
+    total: Cell<usize>,
+    failures: Cell<usize>,
+    first_failure: RefCell<Option<String>>,
+}
+
+impl TimestampParser {
+    pub fn create(option: &LogProcessorOptions) -> Result<Self, String> {
+        let timezone = option
+            .timezone
+            .as_deref()
+            .map(DefaultTimezone::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let formats: Vec<String> = option
+            .timestamp_formats
+            .iter()
+            .cloned()
+            .chain(BUILTIN_FORMATS.iter().map(|format| format.to_string()))
+            .collect();
+        debug!("timestamp formats: {:?}, timezone {:?}", formats, timezone);
+        Ok(TimestampParser {
+            formats,
+            timezone,
+            total: Cell::new(0),
+            failures: Cell::new(0),
+            first_failure: RefCell::new(None),
+        })
+    }
+
+    /// Returns the timestamp in unix milliseconds. Tries RFC 3339, epoch seconds, millis,
+    /// micros or nanos told apart by their number of digits, RFC 2822, then the strptime
+    /// formats. Timestamps without an offset are read in the default timezone.
+    pub fn parse(&self, timestamp: &str) -> Option<i64> {
+        let timestamp = timestamp.trim();
+        if timestamp.is_empty() {
+            return None;
+        }
+        if let Ok(time) = timestamp.parse::<DateTime<Utc>>() {
+            return Some(time.timestamp_millis());
+        }
+        if let Some(time_unix) = parse_epoch(timestamp) {
+            return Some(time_unix);
+        }
+        if let Ok(time) = DateTime::parse_from_rfc2822(timestamp) {
+            return Some(time.timestamp_millis());
+        }
+        self.formats
+            .iter()
+            .find_map(|format| self.parse_format(timestamp, format))
+    }
+
+    fn parse_format(&self, timestamp: &str, format: &str) -> Option<i64> {
+        if let Ok(time) = DateTime::parse_from_str(timestamp, format) {
+            return Some(time.timestamp_millis());
+        }
+        if let Ok(time) = NaiveDateTime::parse_from_str(timestamp, format) {
+            return self.timezone.to_millis(&time);
+        }
+        let date = NaiveDate::parse_from_str(timestamp, format).ok()?;
+        self.timezone.to_millis(&date.and_hms_opt(0, 0, 0)?)
+    }
+
+    /// Counts `log` for the report, as a failure when it has a timestamp but no `time_unix`.
+    /// Logs without any timestamp, like plain text lines, are left out, they have nothing
+    /// a format could fix.
+    pub fn record(&self, log: &LogEntry) {
+        if log.time_unix.is_none() && log.timestamp.trim().is_empty() {
+            return;
+        }
+        self.total.set(self.total.get() + 1);
+        if log.time_unix.is_some() {
+            return;
+        }
+        self.failures.set(self.failures.get() + 1);
+        let mut first_failure = self.first_failure.borrow_mut();
+        if first_failure.is_none() {
+            *first_failure = Some(log.timestamp.clone());
+        }
+    }
+
+    /// Prints how many entries had a timestamp that could not be parsed to stderr, if any.
+    pub fn report(&self) {
+        let failures = self.failures.get();
+        if failures == 0 {
+            return;
+        }
+        eprintln!(
+            "warning: {} of {} logs have a timestamp salog could not parse, e.g. `{}`. add its \
+             format with --timestamp-format",
+            failures,
+            self.total.get(),
+            self.first_failure.borrow().as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Reads whole or fractional epoch numbers, with the unit told apart by the number of
+/// digits: up to 11 seconds, 12 to 14 millis, 15 to 17 micros and more nanos.
+fn parse_epoch(timestamp: &str) -> Option<i64> {
+    let (whole, fraction) = timestamp.split_once('.').unwrap_or((timestamp, ""));
+    if whole.is_empty()
+        || !whole.bytes().all(|byte| byte.is_ascii_digit())
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    let value: f64 = timestamp.parse().ok()?;
+    let millis = match whole.len() {
+        0..=11 => value * 1_000f64,
+        12..=14 => value,
+        15..=17 => value / 1_000f64,
+        _ => value / 1_000_000f64,
+    };
+    Some(millis as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(formats: &[&str], timezone: Option<&str>) -> TimestampParser {
+        TimestampParser {
+            formats: formats
+                .iter()
+                .chain(BUILTIN_FORMATS)
+                .map(|format| format.to_string())
+                .collect(),
+            timezone: timezone
+                .map(|timezone| DefaultTimezone::parse(timezone).unwrap())
+                .unwrap_or_default(),
+            total: Cell::new(0),
+            failures: Cell::new(0),
+            first_failure: RefCell::new(None),
+        }
+    }
+
+    #[test]
+    fn test_record_only_logs_with_a_timestamp() {
+        let timestamps = parser(&[], None);
+        let mut log = LogEntry::from_message("plain text".to_string());
+        timestamps.record(&log);
+        assert_eq!((timestamps.total.get(), timestamps.failures.get()), (0, 0));
+
+        log.timestamp = "yesterday-ish".to_string();
+        timestamps.record(&log);
+        log.timestamp = "1709287200".to_string();
+        log.process_date(&timestamps);
+        timestamps.record(&log);
+        assert_eq!((timestamps.total.get(), timestamps.failures.get()), (2, 1));
+        assert_eq!(
+            timestamps.first_failure.borrow().as_deref(),
+            Some("yesterday-ish")
+        );
+    }
+
+    #[test]
+    fn test_parse_epoch_and_builtin_formats() {
+        let timestamps = parser(&[], None);
+        for timestamp in [
+            "2024-03-01T10:00:00Z",
+            "1709287200",
+            "1709287200.000",
+            "1709287200000",
+            "1709287200000000",
+            "1709287200000000000",
+            "2024-03-01 10:00:00",
+            "2024-03-01 12:00:00 +0200",
+            "Fri, 01 Mar 2024 10:00:00 +0000",
+        ] {
+            assert_eq!(
+                timestamps.parse(timestamp),
+                Some(1709287200000),
+                "{}",
+                timestamp
+            );
+        }
+        assert_eq!(timestamps.parse("yesterday-ish"), None);
+    }
+
+    #[test]
+    fn test_parse_custom_format_in_timezone() {
+        let timestamps = parser(&["%d.%m.%Y %H:%M"], Some("Europe/Berlin"));
+        assert_eq!(timestamps.parse("01.03.2024 11:00"), Some(1709287200000));
+        assert_eq!(
+            parser(&[], Some("+02:00")).parse("2024-03-01 12:00:00"),
+            Some(1709287200000)
+        );
+        assert!(DefaultTimezone::parse("Mars/Olympus").is_err());
+    }
+}
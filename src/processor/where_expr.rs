@@ -0,0 +1,604 @@
+use ipnet::IpNet;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::{
+    cmp::Ordering,
+    iter::Peekable,
+    net::{IpAddr, SocketAddr},
+    str::Chars,
+};
+
+use super::{
+    es_regexp::es_regexp, log_entry::LogEntry, log_processor_options::LogProcessorOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// A `--where` expression, e.g. `status_code >= 500 and url ~ "^/api" and not ip_address in
+/// 10.0.0.0/8`. Fields are the `LogEntry` fields and extra fields. Values compare as numbers
+/// when both sides are numbers, as text otherwise.
+#[derive(Debug, Clone)]
+pub enum WhereExpr {
+    And(Vec<WhereExpr>),
+    Or(Vec<WhereExpr>),
+    Not(Box<WhereExpr>),
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: String,
+    },
+    Matches {
+        field: String,
+        regex: Regex,
+    },
+    InNet {
+        field: String,
+        net: IpNet,
+    },
+    InList {
+        field: String,
+        values: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+impl WhereExpr {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let tokens = tokenize(expression)
+            .map_err(|err| format!("Failed to parse --where `{}`: {}", expression, err))?;
+        let mut parser = ExprParser {
+            tokens,
+            position: 0,
+        };
+        let expr = parser
+            .parse_or()
+            .and_then(|expr| match parser.peek() {
+                None => Ok(expr),
+                Some(token) => Err(format!("unexpected {}", describe(token))),
+            })
+            .map_err(|err| format!("Failed to parse --where `{}`: {}", expression, err))?;
+        Ok(expr)
+    }
+
+    /// Parses every `--where` given, the logs must match all of them.
+    pub fn parse_all(expressions: &[String]) -> Result<Option<Self>, String> {
+        let exprs = expressions
+            .iter()
+            .map(|expression| WhereExpr::parse(expression))
+            .collect::<Result<Vec<WhereExpr>, String>>()?;
+        Ok(all_of(exprs))
+    }
+
+    /// Every `--where` of the options, plus the `--ip` and `--exclude-ip` networks checked
+    /// against `ip_address`.
+    pub fn from_options(option: &LogProcessorOptions) -> Result<Option<Self>, String> {
+        let mut exprs: Vec<WhereExpr> = Self::parse_all(&option.where_filters)?
+            .into_iter()
+            .collect();
+        if !option.ip_filter.is_empty() {
+            exprs.push(in_any_net(IP_FIELD, &option.ip_filter));
+        }
+        if !option.exclude_ip.is_empty() {
+            exprs.push(WhereExpr::Not(Box::new(in_any_net(
+                IP_FIELD,
+                &option.exclude_ip,
+            ))));
+        }
+        Ok(all_of(exprs))
+    }
+
+    pub fn matches(&self, log: &LogEntry) -> bool {
+        match self {
+            WhereExpr::And(exprs) => exprs.iter().all(|expr| expr.matches(log)),
+            WhereExpr::Or(exprs) => exprs.iter().any(|expr| expr.matches(log)),
+            WhereExpr::Not(expr) => !expr.matches(log),
+            WhereExpr::Compare { field, op, value } => match log.field(field) {
+                Some(field_value) => {
+                    let ordering = compare(field, &field_value, value);
+                    match op {
+                        CompareOp::Eq => ordering == Ordering::Equal,
+                        CompareOp::Ne => ordering != Ordering::Equal,
+                        CompareOp::Gt => ordering == Ordering::Greater,
+                        CompareOp::Ge => ordering != Ordering::Less,
+                        CompareOp::Lt => ordering == Ordering::Less,
+                        CompareOp::Le => ordering != Ordering::Greater,
+                    }
+                }
+                None => *op == CompareOp::Ne,
+            },
+            WhereExpr::Matches { field, regex } => log
+                .field(field)
+                .is_some_and(|field_value| regex.is_match(&field_value)),
+            WhereExpr::InNet { field, net } => log
+                .field(field)
+                .and_then(|field_value| parse_ip(&field_value))
+                .is_some_and(|ip| net.contains(&ip)),
+            WhereExpr::InList { field, values } => log.field(field).is_some_and(|field_value| {
+                values
+                    .iter()
+                    .any(|value| compare(field, &field_value, value) == Ordering::Equal)
+            }),
+        }
+    }
+
+    /// Translates the expression into an Elasticsearch query. Text equality uses
+    /// `match_phrase` like `--field`; regexes and aligned IPv4 networks use the `.keyword`
+    /// sub-field of the default dynamic mapping. Other networks can't be searched on that
+    /// mapping and are an error, rather than a query that silently matches nothing.
+    pub fn to_es_query(&self) -> Result<Value, String> {
+        Ok(match self {
+            WhereExpr::And(exprs) => json!({"bool": {"must": es_queries(exprs)?}}),
+            WhereExpr::Or(exprs) => {
+                json!({"bool": {"should": es_queries(exprs)?, "minimum_should_match": 1}})
+            }
+            WhereExpr::Not(expr) => json!({"bool": {"must_not": [expr.to_es_query()?]}}),
+            WhereExpr::Compare { field, op, value } => {
+                let range_op = match op {
+                    CompareOp::Eq => return Ok(json!({"match_phrase": {field: value}})),
+                    CompareOp::Ne => {
+                        return Ok(
+                            json!({"bool": {"must_not": [{"match_phrase": {field: value}}]}}),
+                        )
+                    }
+                    CompareOp::Gt => "gt",
+                    CompareOp::Ge => "gte",
+                    CompareOp::Lt => "lt",
+                    CompareOp::Le => "lte",
+                };
+                let value = value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|number| serde_json::Number::from_f64(number).map(Value::Number))
+                    .unwrap_or_else(|| Value::String(value.clone()));
+                json!({"range": {field: {range_op: value}}})
+            }
+            WhereExpr::Matches { field, regex } => {
+                let (pattern, case_insensitive) = es_regexp(regex.as_str())?;
+                json!({"regexp": {format!("{}.keyword", field): {
+                    "value": pattern,
+                    "case_insensitive": case_insensitive,
+                }}})
+            }
+            WhereExpr::InNet { field, net } => match ipv4_prefix(net) {
+                Some(prefix) => json!({"prefix": {format!("{}.keyword", field): prefix}}),
+                None if net.prefix_len() == net.max_prefix_len() => {
+                    json!({"match_phrase": {field: net.addr().to_string()}})
+                }
+                None => {
+                    return Err(format!(
+                        "Failed to filter {} in {} in elastic search: only single addresses and \
+                         /8, /16 and /24 ipv4 networks can be searched",
+                        field, net
+                    ))
+                }
+            },
+            WhereExpr::InList { field, values } => {
+                let queries: Vec<Value> = values
+                    .iter()
+                    .map(|value| json!({"match_phrase": {field: value}}))
+                    .collect();
+                json!({"bool": {"should": queries, "minimum_should_match": 1}})
+            }
+        })
+    }
+}
+
+const IP_FIELD: &str = "ip_address";
+
+fn all_of(mut exprs: Vec<WhereExpr>) -> Option<WhereExpr> {
+    match exprs.len() {
+        0 | 1 => exprs.pop(),
+        _ => Some(WhereExpr::And(exprs)),
+    }
+}
+
+fn in_any_net(field: &str, nets: &[IpNet]) -> WhereExpr {
+    let mut exprs: Vec<WhereExpr> = nets
+        .iter()
+        .map(|net| WhereExpr::InNet {
+            field: field.to_string(),
+            net: *net,
+        })
+        .collect();
+    match exprs.len() {
+        1 => exprs.pop().unwrap(),
+        _ => WhereExpr::Or(exprs),
+    }
+}
+
+/// Reads an address, also when it is written with its port like `10.0.0.1:5432`.
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+fn es_queries(exprs: &[WhereExpr]) -> Result<Vec<Value>, String> {
+    exprs.iter().map(WhereExpr::to_es_query).collect()
+}
+
+/// Compares as numbers when both sides are numbers. `level` and `http_method` compare
+/// ignoring case, as they are written in either case.
+fn compare(field: &str, field_value: &str, value: &str) -> Ordering {
+    if let (Ok(left), Ok(right)) = (field_value.parse::<f64>(), value.parse::<f64>()) {
+        return left.partial_cmp(&right).unwrap_or(Ordering::Equal);
+    }
+    if field == "level" || field == "http_method" {
+        return field_value.to_lowercase().cmp(&value.to_lowercase());
+    }
+    field_value.cmp(value)
+}
+
+/// Text prefix of an IPv4 network on an octet boundary, e.g. `10.` for `10.0.0.0/8`.
+fn ipv4_prefix(net: &IpNet) -> Option<String> {
+    let IpNet::V4(net) = net else {
+        return None;
+    };
+    let octets = match net.prefix_len() {
+        8 => 1,
+        16 => 2,
+        24 => 3,
+        _ => return None,
+    };
+    let prefix: Vec<String> = net.network().octets()[..octets]
+        .iter()
+        .map(|octet| octet.to_string())
+        .collect();
+    Some(format!("{}.", prefix.join(".")))
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("`{}`", word),
+        Token::Text(text) => format!("\"{}\"", text),
+        Token::Op(op) => format!("`{}`", op),
+        Token::Open => "`(`".to_string(),
+        Token::Close => "`)`".to_string(),
+        Token::Comma => "`,`".to_string(),
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '"' | '\'' => tokens.push(Token::Text(read_quoted(&mut chars)?)),
+            '=' | '!' | '<' | '>' | '~' | '&' | '|' => {
+                chars.next();
+                let op = match (c, chars.peek()) {
+                    ('=', Some('='))
+                    | ('!', Some('='))
+                    | ('<', Some('='))
+                    | ('>', Some('='))
+                    | ('!', Some('~'))
+                    | ('&', Some('&'))
+                    | ('|', Some('|')) => {
+                        let next = chars.next().unwrap();
+                        match (c, next) {
+                            ('=', _) => "==",
+                            ('!', '=') => "!=",
+                            ('!', _) => "!~",
+                            ('<', _) => "<=",
+                            ('>', _) => ">=",
+                            ('&', _) => "and",
+                            _ => "or",
+                        }
+                    }
+                    ('=', _) => "==",
+                    ('!', _) => "not",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    ('~', _) => "~",
+                    (c, _) => return Err(format!("unexpected `{}`", c)),
+                };
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()=,!<>~\"'&|".contains(*c))
+                {
+                    word.push(c);
+                }
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::Op("and"),
+                    "or" => Token::Op("or"),
+                    "not" => Token::Op("not"),
+                    "in" => Token::Op("in"),
+                    _ => Token::Word(word),
+                };
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let quote = chars.next().unwrap();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // keep regex escapes like `\d` as they are
+                Some(c) if c == quote || c == '\\' => text.push(c),
+                Some(c) => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                None => break,
+            },
+            c if c == quote => return Ok(text),
+            c => text.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// Recursive descent over `or` < `and` < `not` < comparisons and parentheses.
+struct ExprParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_string())?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn parse_or(&mut self) -> Result<WhereExpr, String> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.eat(&Token::Op("or")) {
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            WhereExpr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<WhereExpr, String> {
+        let mut exprs = vec![self.parse_not()?];
+        while self.eat(&Token::Op("and")) {
+            exprs.push(self.parse_not()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            WhereExpr::And(exprs)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<WhereExpr, String> {
+        if self.eat(&Token::Op("not")) {
+            return Ok(WhereExpr::Not(Box::new(self.parse_not()?)));
+        }
+        if self.eat(&Token::Open) {
+            let expr = self.parse_or()?;
+            if !self.eat(&Token::Close) {
+                return Err("missing `)`".to_string());
+            }
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<WhereExpr, String> {
+        let field = match self.next()? {
+            Token::Word(field) => field,
+            token => return Err(format!("expected a field name, found {}", describe(&token))),
+        };
+        let op = match self.next()? {
+            Token::Op(op) => op,
+            token => return Err(format!("expected an operator, found {}", describe(&token))),
+        };
+        let op = match op {
+            "==" => CompareOp::Eq,
+            "!=" => CompareOp::Ne,
+            ">" => CompareOp::Gt,
+            ">=" => CompareOp::Ge,
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Le,
+            "~" | "!~" => {
+                let pattern = self.parse_value()?;
+                let regex = Regex::new(&pattern)
+                    .map_err(|err| format!("invalid regex `{}`: {}", pattern, err))?;
+                let expr = WhereExpr::Matches { field, regex };
+                return Ok(if op == "!~" {
+                    WhereExpr::Not(Box::new(expr))
+                } else {
+                    expr
+                });
+            }
+            "in" => return self.parse_in(field),
+            op => return Err(format!("expected an operator, found `{}`", op)),
+        };
+        let value = self.parse_value()?;
+        Ok(WhereExpr::Compare { field, op, value })
+    }
+
+    fn parse_in(&mut self, field: String) -> Result<WhereExpr, String> {
+        if !self.eat(&Token::Open) {
+            let value = self.parse_value()?;
+            if let Ok(net) = value.parse::<IpNet>() {
+                return Ok(WhereExpr::InNet { field, net });
+            }
+            if let Ok(ip) = value.parse::<IpAddr>() {
+                return Ok(WhereExpr::InNet {
+                    field,
+                    net: IpNet::from(ip),
+                });
+            }
+            return Ok(WhereExpr::InList {
+                field,
+                values: vec![value],
+            });
+        }
+        let mut values = vec![self.parse_value()?];
+        while self.eat(&Token::Comma) {
+            values.push(self.parse_value()?);
+        }
+        if !self.eat(&Token::Close) {
+            return Err("missing `)` after the `in` list".to_string());
+        }
+        Ok(WhereExpr::InList { field, values })
+    }
+
+    fn parse_value(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Word(value) | Token::Text(value) => Ok(value),
+            token => Err(format!("expected a value, found {}", describe(&token))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(status_code: &str, url: &str, process_time: f64, ip_address: &str) -> LogEntry {
+        let mut log = LogEntry::from_message(String::new());
+        log.status_code = status_code.parse().ok();
+        log.url = url.to_string();
+        log.process_time = process_time;
+        log.ip_address = ip_address.to_string();
+        log
+    }
+
+    #[test]
+    fn test_where_matches() {
+        let expr = WhereExpr::parse(
+            r#"status_code >= 500 and url ~ "^/api" and process_time > 250 and not ip_address in 10.0.0.0/8"#,
+        )
+        .unwrap();
+        assert!(expr.matches(&entry("503", "/api/users", 300f64, "192.168.1.1")));
+        assert!(!expr.matches(&entry("503", "/api/users", 300f64, "10.1.2.3")));
+        assert!(!expr.matches(&entry("404", "/api/users", 300f64, "192.168.1.1")));
+        assert!(!expr.matches(&entry("503", "/health", 300f64, "192.168.1.1")));
+
+        let expr =
+            WhereExpr::parse("(level = error || http_method in (post, put)) && tenant != acme")
+                .unwrap();
+        let mut log = entry("200", "/", 0f64, "");
+        log.set_field("http_method", "PUT");
+        assert!(expr.matches(&log));
+        log.extra_fields
+            .insert("tenant".to_string(), Value::String("acme".to_string()));
+        assert!(!expr.matches(&log));
+    }
+
+    #[test]
+    fn test_where_parse_errors() {
+        assert!(WhereExpr::parse("status_code >=").is_err());
+        assert!(WhereExpr::parse("(status_code > 1").is_err());
+        assert!(WhereExpr::parse("url ~ \"[\"").is_err());
+        assert!(WhereExpr::parse("url \"x\"").is_err());
+        assert!(WhereExpr::parse("a = 1 b = 2").is_err());
+    }
+
+    #[test]
+    fn test_where_to_es_query() {
+        let expr = WhereExpr::parse(
+            r#"status_code >= 500 and url ~ "^/api" and not ip_address in 10.0.0.0/8"#,
+        )
+        .unwrap();
+        assert_eq!(
+            expr.to_es_query().unwrap(),
+            json!({"bool": {"must": [
+                {"range": {"status_code": {"gte": 500.0}}},
+                {"regexp": {"url.keyword": {"value": "/api.*", "case_insensitive": false}}},
+                {"bool": {"must_not": [{"prefix": {"ip_address.keyword": "10."}}]}},
+            ]}})
+        );
+    }
+
+    #[test]
+    fn test_ip_filter_matches() {
+        let expr = WhereExpr::And(vec![
+            in_any_net(
+                IP_FIELD,
+                &[
+                    "10.0.0.0/8".parse().unwrap(),
+                    "2001:db8::/32".parse().unwrap(),
+                ],
+            ),
+            WhereExpr::Not(Box::new(in_any_net(
+                IP_FIELD,
+                &["10.0.0.1/32".parse().unwrap()],
+            ))),
+        ]);
+        assert!(expr.matches(&entry("200", "/", 0.0, "10.1.2.3")));
+        assert!(expr.matches(&entry("200", "/", 0.0, "10.1.2.3:5432")));
+        assert!(expr.matches(&entry("200", "/", 0.0, "2001:db8::7")));
+        assert!(!expr.matches(&entry("200", "/", 0.0, "10.0.0.1")));
+        assert!(!expr.matches(&entry("200", "/", 0.0, "192.168.0.1")));
+        assert!(!expr.matches(&entry("200", "/", 0.0, "")));
+    }
+
+    #[test]
+    fn test_ip_filter_to_es_query() {
+        let host = in_any_net(IP_FIELD, &["10.0.0.1".parse::<IpAddr>().unwrap().into()]);
+        assert_eq!(
+            WhereExpr::Not(Box::new(host)).to_es_query(),
+            Ok(json!({"bool": {"must_not": [{"match_phrase": {"ip_address": "10.0.0.1"}}]}}))
+        );
+        for net in ["10.16.0.0/12", "2001:db8::/32"] {
+            let expr = WhereExpr::Not(Box::new(in_any_net(IP_FIELD, &[net.parse().unwrap()])));
+            assert!(expr.to_es_query().is_err(), "{}", net);
+        }
+    }
+}